@@ -3,7 +3,11 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use internnft::nft::{ExecuteMsg, InstantiateMsg, InternTokensResponse, QueryMsg};
+use internnft::nft::{
+    ExecuteMsg, ExportStateResponse, InstantiateMsg, InternNftInfoResponse, InternTokenStakingInfo,
+    InternTokensResponse, InternTokensStakingResponse, MigrateMsg, MintStatusResponse, PauseFlags,
+    QueryMsg, StartingTraitsPreviewResponse, TraitUpdatersResponse, WalletLimitExemptionsResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,5 +18,15 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(InternNftInfoResponse), &out_dir);
     export_schema(&schema_for!(InternTokensResponse), &out_dir);
+    export_schema(&schema_for!(InternTokenStakingInfo), &out_dir);
+    export_schema(&schema_for!(InternTokensStakingResponse), &out_dir);
+    export_schema(&schema_for!(MintStatusResponse), &out_dir);
+    export_schema(&schema_for!(PauseFlags), &out_dir);
+    export_schema(&schema_for!(StartingTraitsPreviewResponse), &out_dir);
+    export_schema(&schema_for!(TraitUpdatersResponse), &out_dir);
+    export_schema(&schema_for!(WalletLimitExemptionsResponse), &out_dir);
+    export_schema(&schema_for!(ExportStateResponse), &out_dir);
 }
@@ -2,7 +2,8 @@ use cosmwasm_std::{
     entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
 };
 use cw2::{get_contract_version, set_contract_version};
-use internnft::nft::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use internnft::migrations::parse_version;
+use internnft::nft::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, TraitFilter};
 
 use crate::error::ContractError;
 use crate::execute as ExecHandler;
@@ -31,17 +32,63 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Mint {} => ExecHandler::execute_mint(deps, env, info),
+        ExecuteMsg::Mint { signature, proof } => {
+            ExecHandler::execute_mint(deps, env, info, signature, proof)
+        }
+        ExecuteMsg::BatchMint { recipients } => {
+            ExecHandler::execute_batch_mint(deps, env, info, recipients)
+        }
+        ExecuteMsg::GrantTraitUpdater { address, traits } => {
+            ExecHandler::execute_grant_trait_updater(deps, info, address, traits)
+        }
+        ExecuteMsg::RevokeTraitUpdater { address } => {
+            ExecHandler::execute_revoke_trait_updater(deps, info, address)
+        }
+        ExecuteMsg::AddWalletLimitExemption { address } => {
+            ExecHandler::execute_add_wallet_limit_exemption(deps, info, address)
+        }
+        ExecuteMsg::RemoveWalletLimitExemption { address } => {
+            ExecHandler::execute_remove_wallet_limit_exemption(deps, info, address)
+        }
+        ExecuteMsg::ImportTokens { tokens, minted } => {
+            ExecHandler::execute_import_tokens(deps, info, tokens, minted)
+        }
+        ExecuteMsg::SealImport {} => ExecHandler::execute_seal_import(deps, info),
+        ExecuteMsg::ResumeMigration { limit } => {
+            ExecHandler::execute_resume_migration(deps, info, limit)
+        }
+        ExecuteMsg::UpdatePauseFlags { flags } => {
+            ExecHandler::execute_update_pause_flags(deps, info, flags)
+        }
+        ExecuteMsg::Burn { token_id } => ExecHandler::execute_burn(deps, env, info, token_id),
         ExecuteMsg::UpdateConfig { config } => {
             ExecHandler::execute_update_config(deps, info, config)
         }
+        ExecuteMsg::UpdateMetadataConfig { metadata } => {
+            ExecHandler::execute_update_metadata_config(deps, info, metadata)
+        }
         ExecuteMsg::Withdraw { amount } => ExecHandler::execute_withdraw(deps, env, info, amount),
+        ExecuteMsg::ProposeNewOwner { owner, expires } => {
+            ExecHandler::execute_propose_new_owner(deps, env, info, owner, expires)
+        }
+        ExecuteMsg::AcceptOwnership {} => ExecHandler::execute_accept_ownership(deps, env, info),
+        ExecuteMsg::CancelOwnershipProposal {} => {
+            ExecHandler::execute_cancel_ownership_proposal(deps, info)
+        }
         ExecuteMsg::UpdateTrait {
             token_id,
             exp,
             gold,
             stamina,
         } => ExecHandler::execute_update_traits(deps, env, info, token_id, exp, gold, stamina),
+        ExecuteMsg::AdjustTraits {
+            token_id,
+            experience,
+            gold,
+            stamina,
+        } => {
+            ExecHandler::execute_adjust_traits(deps, env, info, token_id, experience, gold, stamina)
+        }
         _ => ExecHandler::cw721_base_execute(deps, env, info, msg),
     }
 }
@@ -50,25 +97,108 @@ pub fn execute(
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&QueryHandler::query_config(deps)?),
-        QueryMsg::InternNftInfo { token_id } => {
-            to_binary(&QueryHandler::query_intern_nft_info(deps, token_id)?)
+        QueryMsg::MetadataConfig {} => to_binary(&QueryHandler::query_metadata_config(deps)?),
+        QueryMsg::PauseStatus {} => to_binary(&QueryHandler::query_pause_status(deps)?),
+        QueryMsg::Owner {} => to_binary(&QueryHandler::query_owner(deps)?),
+        QueryMsg::InternNftInfo {
+            token_id,
+            include_staking,
+        } => {
+            if include_staking {
+                to_binary(&QueryHandler::query_intern_nft_staking_info(
+                    deps, token_id,
+                )?)
+            } else {
+                to_binary(&QueryHandler::query_intern_nft_info(deps, token_id)?)
+            }
         }
         QueryMsg::InternTokens {
             owner,
             start_after,
+            start_before,
+            limit,
+            order,
+            include_staking,
+        } => {
+            if include_staking {
+                to_binary(&QueryHandler::query_intern_tokens_staking_info(
+                    deps,
+                    owner,
+                    start_after,
+                    start_before,
+                    limit,
+                    order,
+                )?)
+            } else {
+                to_binary(&QueryHandler::query_intern_tokens(
+                    deps,
+                    owner,
+                    start_after,
+                    start_before,
+                    limit,
+                    order,
+                )?)
+            }
+        }
+        QueryMsg::AllInternTokens {
+            start_after,
+            start_before,
             limit,
-        } => to_binary(&QueryHandler::query_intern_tokens(
+            order,
+        } => to_binary(&QueryHandler::query_all_intern_tokens(
             deps,
-            owner,
             start_after,
+            start_before,
             limit,
+            order,
         )?),
-        QueryMsg::AllInternTokens { start_after, limit } => to_binary(
-            &QueryHandler::query_all_intern_tokens(deps, start_after, limit)?,
-        ),
         QueryMsg::NumTokensForOwner { owner } => {
             to_binary(&QueryHandler::query_num_tokens_for_owner(deps, owner)?)
         }
+        QueryMsg::MintStatus { address } => {
+            to_binary(&QueryHandler::query_mint_status(deps, env, address)?)
+        }
+        QueryMsg::StartingTraitsPreview { ranges, samples } => to_binary(
+            &QueryHandler::query_starting_traits_preview(deps, env, ranges, samples)?,
+        ),
+        QueryMsg::SearchTokens {
+            owner,
+            min_experience,
+            max_experience,
+            min_gold,
+            min_stamina,
+            start_after,
+            limit,
+        } => {
+            let filter = TraitFilter {
+                min_experience,
+                max_experience,
+                min_gold,
+                min_stamina,
+            };
+            to_binary(&QueryHandler::query_search_tokens(
+                deps,
+                owner,
+                filter,
+                start_after,
+                limit,
+            )?)
+        }
+        QueryMsg::TopByExperience { start_after, limit } => to_binary(
+            &QueryHandler::query_top_by_experience(deps, start_after, limit)?,
+        ),
+        QueryMsg::TopByGold { start_after, limit } => {
+            to_binary(&QueryHandler::query_top_by_gold(deps, start_after, limit)?)
+        }
+        QueryMsg::TraitUpdaters { start_after, limit } => to_binary(
+            &QueryHandler::query_trait_updaters(deps, start_after, limit)?,
+        ),
+        QueryMsg::WalletLimitExemptions { start_after, limit } => to_binary(
+            &QueryHandler::query_wallet_limit_exemptions(deps, start_after, limit)?,
+        ),
+        QueryMsg::ExportState { start_after, limit } => {
+            to_binary(&QueryHandler::query_export_state(deps, start_after, limit)?)
+        }
         _ => QueryHandler::cw721_base_query(deps, env, msg),
     }
 }
@@ -81,8 +211,14 @@ pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response>
             "can't migrate to contract with different name",
         ));
     }
+    let from_version = parse_version(&version.version)?;
+    if from_version > parse_version(CONTRACT_VERSION)? {
+        return Err(StdError::generic_err(
+            "can't migrate to an older contract version",
+        ));
+    }
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    ExecHandler::migrate(deps, env, msg)
+    ExecHandler::migrate(deps, env, msg, &from_version)
 }
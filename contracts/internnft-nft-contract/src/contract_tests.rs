@@ -1,17 +1,39 @@
 #![cfg(test)]
+use std::collections::HashMap;
 use std::str;
 
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{BankMsg, Binary, Coin, DepsMut};
-use internnft::nft::{Config, InstantiateMsg};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    coins, from_binary, from_slice, to_binary, Addr, BankMsg, Binary, Coin, ContractResult, Deps,
+    DepsMut, Empty, Env, OwnedDeps, Querier, QuerierResult, QueryRequest, Response, StdError,
+    StdResult, SystemResult, WasmQuery,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw721::{ContractInfoResponse, Expiration};
+use cw721_base::Cw721Contract;
+use cw_storage_plus::Map;
+use internnft::nft::{
+    Config, ExecuteMsg, ExportedToken, InstantiateMsg, InternBurnedMsg, InternExtension,
+    InternNftInfoResponse, InternTokenInfo, InternTokenStakingInfo, InternTokensResponse,
+    InternTokensStakingResponse, MetadataConfig, MigrateMsg, MintPhase, MintPhaseKind,
+    MintStatusResponse, OwnerResponse, OwnershipProposal, PauseFlags, QueryMsg, TraitFilter,
+    TraitKind, TraitRange, TraitRanges, TraitUpdater,
+};
+use internnft::staking::{QueryMsg as StakingQueryMsg, StakingInfo};
+use internnft::token_id::TokenId;
 
-use crate::contract::instantiate;
+use crate::allowlist::test::{merkle_tree, sign, test_public_key};
+use crate::contract::{instantiate, migrate, query};
 use crate::error::ContractError;
 use crate::execute as ExecHandler;
 use crate::query as QueryHandler;
+use crate::state::{tokens, OWNER_TOKEN_COUNTS};
 
 const OWNER: &str = "owner";
 const NONOWNER: &str = "nonowner";
+const NONOWNER2: &str = "nonowner2";
 
 fn mock_config() -> Config {
     Config {
@@ -19,6 +41,12 @@ fn mock_config() -> Config {
         token_supply: 10000,
         wallet_limit: 5,
         staking_contract: "staking_contract".to_string(),
+        starting_traits: TraitRanges::default(),
+        allowlist_public_key: None,
+        allowlist_merkle_root: None,
+        presale: false,
+        mint_phases: vec![],
+        burn_hook: None,
     }
 }
 
@@ -29,7 +57,11 @@ fn setup_contract(
     wallet_limit: Option<u32>,
 ) {
     let mut msg = InstantiateMsg {
+        name: "intern".to_string(),
+        symbol: "intern".to_string(),
+        token_prefix: "intern #".to_string(),
         config: mock_config(),
+        metadata: MetadataConfig::default(),
     };
     if let Some(mint_fee) = mint_fee {
         msg.config.mint_fee = mint_fee;
@@ -53,6 +85,66 @@ fn as_json(binary: &Binary) -> serde_json::Value {
     serde_json::from_str(decoded_str).unwrap()
 }
 
+#[test]
+fn custom_collection() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        name: "Summer Interns".to_string(),
+        symbol: "SUMMER".to_string(),
+        token_prefix: "summer intern #".to_string(),
+        config: mock_config(),
+        metadata: MetadataConfig::default(),
+    };
+
+    for token_prefix in ["", "summer intern 2"].iter() {
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            InstantiateMsg {
+                token_prefix: token_prefix.to_string(),
+                ..msg.clone()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("token prefix must not be empty or end with a digit")
+        );
+    }
+
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    let res = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "token_id" && attr.value == "1"));
+
+    let info: ContractInfoResponse = from_binary(
+        &QueryHandler::cw721_base_query(deps.as_ref(), mock_env(), QueryMsg::ContractInfo {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(info.name, "Summer Interns");
+    assert_eq!(info.symbol, "SUMMER");
+
+    let intern = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+    assert_eq!(intern.name, "summer intern #1");
+
+    let tokens =
+        QueryHandler::query_tokens(deps.as_ref(), NONOWNER.to_string(), None, None, None, None)
+            .unwrap()
+            .tokens;
+    assert_eq!(tokens, vec!["1".to_string()]);
+}
+
 #[test]
 fn update_and_query_config() {
     let initial_config = mock_config();
@@ -132,3 +224,2705 @@ fn withdraw() {
         .into()
     )
 }
+
+#[test]
+fn mint() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), Some(Coin::new(1000, "uluna")), None, None);
+
+    // mint with the exact fee
+    let res = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[Coin::new(1000, "uluna")]),
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "token_id" && attr.value == "1"));
+
+    // token ids are assigned sequentially
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[Coin::new(2000, "uluna")]),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let token = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+    assert_eq!(token.owner.to_string(), NONOWNER.to_string());
+    assert_eq!(
+        token.extension,
+        InternExtension {
+            experience: 0,
+            gold: 0,
+            stamina: 100,
+        }
+    );
+    let token = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(2)).unwrap();
+    assert_eq!(token.owner.to_string(), NONOWNER2.to_string());
+
+    // the owner mints for free
+    let res =
+        ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+            .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "token_id" && attr.value == "3"));
+}
+
+#[test]
+fn mint_insufficient_funds() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), Some(Coin::new(1000, "uluna")), None, None);
+
+    let insufficient_funds = ContractError::Std(StdError::generic_err("insufficient funds sent"));
+
+    // no funds
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, insufficient_funds);
+
+    // not enough funds
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[Coin::new(999, "uluna")]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, insufficient_funds);
+
+    // wrong denom
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[Coin::new(1000, "uusd")]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, insufficient_funds);
+
+    // nothing was minted
+    let res =
+        QueryHandler::query_num_tokens_for_owner(deps.as_ref(), NONOWNER.to_string()).unwrap();
+    assert_eq!(res.count, 0);
+}
+
+#[test]
+fn mint_wallet_limit() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, Some(2));
+
+    for _ in 0..2 {
+        let _ = ExecHandler::execute_mint(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(NONOWNER, &[]),
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    // wallet is full
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimit {});
+
+    // other wallets can still mint
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn mint_token_supply() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, Some(2), None);
+
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+
+    // supply is exhausted, even for the owner
+    let err =
+        ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+            .unwrap_err();
+    assert_eq!(err, ContractError::SupplyExhausted {});
+}
+
+fn mock_trait_ranges() -> TraitRanges {
+    TraitRanges {
+        experience: TraitRange { min: 0, max: 50 },
+        gold: TraitRange { min: 10, max: 1000 },
+        stamina: TraitRange { min: 80, max: 120 },
+    }
+}
+
+#[test]
+fn mint_random_traits() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let mut config = mock_config();
+    config.starting_traits = mock_trait_ranges();
+    let _ =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config).unwrap();
+
+    for _ in 0..3 {
+        let _ =
+            ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+                .unwrap();
+    }
+
+    let res = QueryHandler::query_all_intern_tokens(deps.as_ref(), None, None, None, None).unwrap();
+    assert_eq!(res.tokens.len(), 3);
+    for token in res.tokens.iter() {
+        assert!(token.extension.experience <= 50);
+        assert!((10..=1000).contains(&token.extension.gold));
+        assert!((80..=120).contains(&token.extension.stamina));
+    }
+    // interns minted in the same block still differ
+    assert_ne!(res.tokens[0].extension, res.tokens[1].extension);
+    assert_ne!(res.tokens[1].extension, res.tokens[2].extension);
+}
+
+#[test]
+fn update_config_rejects_invalid_trait_ranges() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let mut config = mock_config();
+    config.starting_traits.stamina = TraitRange { min: 10, max: 5 };
+    let err = ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "invalid stamina range: min is greater than max"
+        ))
+    );
+}
+
+#[test]
+fn starting_traits_preview() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    // defaults to the configured ranges
+    let res =
+        QueryHandler::query_starting_traits_preview(deps.as_ref(), mock_env(), None, None).unwrap();
+    assert_eq!(res.ranges, TraitRanges::default());
+    assert_eq!(res.samples.len(), 10);
+    assert!(res.samples.iter().all(|traits| *traits
+        == InternExtension {
+            experience: 0,
+            gold: 0,
+            stamina: 100,
+        }));
+
+    // candidate ranges can be previewed without updating the config
+    let res = QueryHandler::query_starting_traits_preview(
+        deps.as_ref(),
+        mock_env(),
+        Some(mock_trait_ranges()),
+        Some(1000),
+    )
+    .unwrap();
+    assert_eq!(res.ranges, mock_trait_ranges());
+    assert_eq!(res.samples.len(), 100);
+    assert!(res.samples.iter().all(|traits| traits.experience <= 50
+        && (10..=1000).contains(&traits.gold)
+        && (80..=120).contains(&traits.stamina)));
+
+    let res = QueryHandler::query_config(deps.as_ref()).unwrap();
+    assert_eq!(res.starting_traits, TraitRanges::default());
+}
+
+fn setup_presale(deps: DepsMut) {
+    let mut config = mock_config();
+    config.allowlist_public_key = Some(test_public_key());
+    config.presale = true;
+    let _ = ExecHandler::execute_update_config(deps, mock_info(OWNER, &[]), config).unwrap();
+}
+
+#[test]
+fn presale_mint() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    setup_presale(deps.as_mut());
+
+    // wallets without a signature can't mint during the presale
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::AllowlistOnly {});
+
+    // but the owner can
+    let _ = ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+        .unwrap();
+
+    // allowlisted wallets can mint up to their quota
+    let signature = sign(NONOWNER, 1, 2);
+    for _ in 0..2 {
+        let _ = ExecHandler::execute_mint(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(NONOWNER, &[]),
+            Some(signature.clone()),
+            None,
+        )
+        .unwrap();
+    }
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        Some(signature),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::AllowlistQuotaExceeded {});
+
+    // a fresh nonce grants a new quota
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        Some(sign(NONOWNER, 2, 1)),
+        None,
+    )
+    .unwrap();
+
+    let res =
+        QueryHandler::query_num_tokens_for_owner(deps.as_ref(), NONOWNER.to_string()).unwrap();
+    assert_eq!(res.count, 3);
+}
+
+#[test]
+fn presale_mint_invalid_signature() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    // signatures are rejected while no allowlist key is configured
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        Some(sign(NONOWNER, 1, 1)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature {});
+
+    setup_presale(deps.as_mut());
+
+    // signatures can't be reused by another wallet
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        Some(sign(NONOWNER, 1, 1)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature {});
+}
+
+#[test]
+fn update_config_rejects_invalid_public_key() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let mut config = mock_config();
+    config.allowlist_public_key = Some(Binary::from(b"not a key".to_vec()));
+    let err = ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("invalid allowlist public key"))
+    );
+}
+
+#[test]
+fn merkle_presale_mint() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let (root, proofs) = merkle_tree(&[(NONOWNER, 2), (NONOWNER2, 1), ("other", 5)]);
+    let mut config = mock_config();
+    config.allowlist_merkle_root = Some(root);
+    config.presale = true;
+    let _ =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config).unwrap();
+
+    // allowlisted wallets can mint up to their quota
+    for _ in 0..2 {
+        let _ = ExecHandler::execute_mint(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(NONOWNER, &[]),
+            None,
+            Some(proofs[0].clone()),
+        )
+        .unwrap();
+    }
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        Some(proofs[0].clone()),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::AllowlistQuotaExceeded {});
+
+    // claiming a bigger quota invalidates the proof
+    let mut inflated = proofs[1].clone();
+    inflated.quota = 5;
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        None,
+        Some(inflated),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleProof {});
+
+    // proofs can't be used by another wallet
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        None,
+        Some(proofs[2].clone()),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleProof {});
+
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        None,
+        Some(proofs[1].clone()),
+    )
+    .unwrap();
+
+    // signatures and proofs can't be combined
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        Some(sign(NONOWNER2, 1, 1)),
+        Some(proofs[1].clone()),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "expected either an allowlist signature or a merkle proof, not both"
+        ))
+    );
+}
+
+#[test]
+fn merkle_mint_without_root() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let (_, proofs) = merkle_tree(&[(NONOWNER, 2)]);
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        Some(proofs[0].clone()),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleProof {});
+
+    // roots must be sha256 hashes
+    let mut config = mock_config();
+    config.allowlist_merkle_root = Some(Binary::from(vec![0u8; 20]));
+    let err = ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "invalid allowlist merkle root: expected a sha256 hash"
+        ))
+    );
+}
+
+fn env_at(seconds_from_now: i64) -> Env {
+    let mut env = mock_env();
+    env.block.time = if seconds_from_now >= 0 {
+        env.block.time.plus_seconds(seconds_from_now as u64)
+    } else {
+        env.block
+            .time
+            .minus_seconds(seconds_from_now.unsigned_abs())
+    };
+    env
+}
+
+fn mock_mint_phases() -> Vec<MintPhase> {
+    let now = mock_env().block.time;
+    vec![
+        MintPhase {
+            kind: MintPhaseKind::Allowlist,
+            start: now,
+            end: now.plus_seconds(100),
+            price: Coin::new(100, "uluna"),
+            wallet_cap: 1,
+            supply_cap: 2,
+        },
+        MintPhase {
+            kind: MintPhaseKind::Closed,
+            start: now.plus_seconds(100),
+            end: now.plus_seconds(200),
+            price: Coin::new(0, "uluna"),
+            wallet_cap: 0,
+            supply_cap: 0,
+        },
+        MintPhase {
+            kind: MintPhaseKind::Public,
+            start: now.plus_seconds(200),
+            end: now.plus_seconds(300),
+            price: Coin::new(200, "uluna"),
+            wallet_cap: 2,
+            supply_cap: 10,
+        },
+    ]
+}
+
+fn setup_mint_phases(deps: DepsMut) {
+    let mut config = QueryHandler::query_config(deps.as_ref()).unwrap();
+    config.allowlist_public_key = Some(test_public_key());
+    config.mint_phases = mock_mint_phases();
+    let _ = ExecHandler::execute_update_config(deps, mock_info(OWNER, &[]), config).unwrap();
+}
+
+#[test]
+fn mint_phases() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    setup_mint_phases(deps.as_mut());
+
+    let allowlist_fee = coins(100, "uluna");
+    let public_fee = coins(200, "uluna");
+
+    // minting is closed before the first phase
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(-1),
+        mock_info(NONOWNER, &allowlist_fee),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MintClosed {});
+
+    // the allowlist phase requires a signature
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(0),
+        mock_info(NONOWNER, &allowlist_fee),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::AllowlistOnly {});
+
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(0),
+        mock_info(NONOWNER, &allowlist_fee),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap();
+
+    // the phase's wallet cap applies on top of the allowlist quota
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(50),
+        mock_info(NONOWNER, &allowlist_fee),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PhaseWalletLimit {});
+
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(50),
+        mock_info(NONOWNER2, &allowlist_fee),
+        Some(sign(NONOWNER2, 1, 5)),
+        None,
+    )
+    .unwrap();
+
+    // the phase's supply cap is reached
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(99),
+        mock_info("other", &allowlist_fee),
+        Some(sign("other", 1, 5)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PhaseSupplyExhausted {});
+
+    // the owner isn't bound by the schedule
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(150),
+        mock_info(OWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+
+    // explicitly closed phase
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(150),
+        mock_info(NONOWNER, &public_fee),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MintClosed {});
+
+    // the public phase charges its own price
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(200),
+        mock_info(NONOWNER, &allowlist_fee),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("insufficient funds sent"))
+    );
+
+    for _ in 0..2 {
+        let _ = ExecHandler::execute_mint(
+            deps.as_mut(),
+            env_at(200),
+            mock_info(NONOWNER, &public_fee),
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(200),
+        mock_info(NONOWNER, &public_fee),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PhaseWalletLimit {});
+
+    // minting is closed after the last phase
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(300),
+        mock_info(NONOWNER2, &public_fee),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MintClosed {});
+}
+
+#[test]
+fn mint_phase_counts_follow_schedule_edits() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    setup_mint_phases(deps.as_mut());
+    let allowlist_fee = coins(100, "uluna");
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(0),
+        mock_info(NONOWNER, &allowlist_fee),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap();
+
+    // inserting a phase shifts the allowlist phase to the second position
+    let mut config = QueryHandler::query_config(deps.as_ref()).unwrap();
+    let now = mock_env().block.time;
+    let mut earlier = config.mint_phases[2].clone();
+    earlier.start = now.minus_seconds(200);
+    earlier.end = now.minus_seconds(100);
+    config.mint_phases.insert(0, earlier);
+    let _ =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config).unwrap();
+
+    let res = QueryHandler::query_mint_status(deps.as_ref(), env_at(0), Some(NONOWNER.to_string()))
+        .unwrap();
+    assert_eq!(res.phase_index, Some(1));
+    assert_eq!(res.phase_remaining_supply, Some(1));
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(50),
+        mock_info(NONOWNER, &allowlist_fee),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PhaseWalletLimit {});
+}
+
+#[test]
+fn mint_status() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, Some(100), None);
+
+    // without phases, the status follows mint_fee
+    let res =
+        QueryHandler::query_mint_status(deps.as_ref(), mock_env(), Some(NONOWNER.to_string()))
+            .unwrap();
+    assert_eq!(
+        res,
+        MintStatusResponse {
+            phase: MintPhaseKind::Public,
+            phase_index: None,
+            phase_end: None,
+            price: Some(Coin::new(0, "uluna")),
+            minted: 0,
+            remaining_supply: 100,
+            phase_remaining_supply: None,
+            wallet_remaining: Some(5),
+        }
+    );
+
+    setup_mint_phases(deps.as_mut());
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        env_at(0),
+        mock_info(NONOWNER, &[Coin::new(100, "uluna")]),
+        Some(sign(NONOWNER, 1, 5)),
+        None,
+    )
+    .unwrap();
+
+    let res = QueryHandler::query_mint_status(deps.as_ref(), env_at(0), Some(NONOWNER.to_string()))
+        .unwrap();
+    assert_eq!(
+        res,
+        MintStatusResponse {
+            phase: MintPhaseKind::Allowlist,
+            phase_index: Some(0),
+            phase_end: Some(mock_env().block.time.plus_seconds(100)),
+            price: Some(Coin::new(100, "uluna")),
+            minted: 1,
+            remaining_supply: 99,
+            phase_remaining_supply: Some(1),
+            wallet_remaining: Some(0),
+        }
+    );
+
+    let res = QueryHandler::query_mint_status(deps.as_ref(), env_at(150), None).unwrap();
+    assert_eq!(
+        res,
+        MintStatusResponse {
+            phase: MintPhaseKind::Closed,
+            phase_index: Some(1),
+            phase_end: Some(mock_env().block.time.plus_seconds(200)),
+            price: None,
+            minted: 1,
+            remaining_supply: 99,
+            phase_remaining_supply: None,
+            wallet_remaining: None,
+        }
+    );
+
+    let res =
+        QueryHandler::query_mint_status(deps.as_ref(), env_at(250), Some(NONOWNER.to_string()))
+            .unwrap();
+    assert_eq!(res.phase, MintPhaseKind::Public);
+    assert_eq!(res.phase_remaining_supply, Some(10));
+    assert_eq!(res.wallet_remaining, Some(2));
+}
+
+#[test]
+fn update_config_rejects_invalid_mint_phases() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let mut config = mock_config();
+    config.mint_phases = mock_mint_phases();
+    config.mint_phases[1].start = config.mint_phases[1].end;
+    let err =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config.clone())
+            .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "invalid mint phase 1: start must be before end"
+        ))
+    );
+
+    config.mint_phases = mock_mint_phases();
+    config.mint_phases[2].start = config.mint_phases[1].start;
+    let err = ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "invalid mint phase 2: overlaps the previous phase"
+        ))
+    );
+}
+
+#[test]
+fn batch_mint() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(
+        deps.as_mut(),
+        Some(Coin::new(1000, "uluna")),
+        Some(5),
+        Some(1),
+    );
+
+    let recipients = vec![(NONOWNER.to_string(), 2), (NONOWNER2.to_string(), 1)];
+
+    // nonowner can't batch mint
+    let err = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        recipients.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // owner airdrops without paying and regardless of wallet limits
+    let res = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        recipients,
+    )
+    .unwrap();
+    let minted: Vec<(String, String)> = res
+        .events
+        .iter()
+        .map(|event| {
+            assert_eq!(event.ty, "mint");
+            (
+                event.attributes[0].value.clone(),
+                event.attributes[1].value.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        minted,
+        vec![
+            (NONOWNER.to_string(), "1".to_string()),
+            (NONOWNER.to_string(), "2".to_string()),
+            (NONOWNER2.to_string(), "3".to_string()),
+        ]
+    );
+
+    let res =
+        QueryHandler::query_num_tokens_for_owner(deps.as_ref(), NONOWNER.to_string()).unwrap();
+    assert_eq!(res.count, 2);
+
+    // batches that would exceed the supply fail without minting anything
+    let err = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1), (NONOWNER2.to_string(), 2)],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::BatchExceedsSupply {
+            requested: 3,
+            remaining: 2
+        }
+    );
+    let res = QueryHandler::query_all_intern_tokens(deps.as_ref(), None, None, None, None).unwrap();
+    assert_eq!(res.tokens.len(), 3);
+}
+
+#[test]
+fn burn() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, Some(3), None);
+
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![
+            (NONOWNER.to_string(), 1),
+            ("staking_contract".to_string(), 1),
+        ],
+    )
+    .unwrap();
+
+    // blocks full token identifiers
+    let err = from_slice::<ExecuteMsg>(br#"{"burn":{"token_id":"intern #1"}}"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected numeric token identifier"));
+
+    // only the owner or an operator can burn
+    let err = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        TokenId::new(1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // staked tokens can't be burned
+    let err = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(2),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TokenStaked {});
+
+    let res = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        TokenId::new(1),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "token_id" && attr.value == "1"));
+
+    QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap_err();
+    let res =
+        QueryHandler::query_num_tokens_for_owner(deps.as_ref(), NONOWNER.to_string()).unwrap();
+    assert_eq!(res.count, 0);
+    let num_tokens = Cw721Contract::<InternExtension, Empty>::default()
+        .token_count(&deps.storage)
+        .unwrap();
+    assert_eq!(num_tokens, 1);
+
+    // burned ids aren't reused and burned tokens still count towards the supply
+    let res =
+        ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+            .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "token_id" && attr.value == "3"));
+    let err =
+        ExecHandler::execute_mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), None, None)
+            .unwrap_err();
+    assert_eq!(err, ContractError::SupplyExhausted {});
+}
+
+#[test]
+fn burn_hook() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    // the hook must be a valid address
+    let mut config = mock_config();
+    config.burn_hook = Some("x".to_string());
+    let err = ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config)
+        .unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+
+    let mut config = mock_config();
+    config.burn_hook = Some("gold_contract".to_string());
+    let _ =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), config).unwrap();
+
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+    let token = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+
+    let res = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        TokenId::new(1),
+    )
+    .unwrap();
+    let expected = InternBurnedMsg {
+        token_id: TokenId::new(1),
+        owner: NONOWNER.to_string(),
+        experience: token.extension.experience,
+        gold: token.extension.gold,
+    };
+    assert_eq!(
+        res.messages[0].msg,
+        expected.into_cosmos_msg("gold_contract").unwrap()
+    );
+}
+
+// answers staking info queries against the staking contract from a fixed set of records
+struct StakingQuerier {
+    base: MockQuerier,
+    entries: HashMap<TokenId, StakingInfo>,
+}
+
+impl Querier for StakingQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice(bin_request) {
+            Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart { contract_addr, msg }))
+                if contract_addr == "staking_contract" =>
+            {
+                let value = match from_binary(&msg).unwrap() {
+                    StakingQueryMsg::GetStakingInfo { token_id } => self.entries.get(&token_id),
+                    StakingQueryMsg::GetConfig {} => panic!("unexpected staking query"),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&value).unwrap()))
+            }
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
+
+#[test]
+fn include_staking() {
+    let staking_info = StakingInfo {
+        staked: true,
+        last_action_block_time: 12345,
+        current_stamina: 80,
+        token_id: TokenId::new(1),
+        owner: Addr::unchecked(NONOWNER),
+        staking_type: "gold".to_string(),
+    };
+    let mut entries = HashMap::new();
+    entries.insert(TokenId::new(1), staking_info.clone());
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: StakingQuerier {
+            base: MockQuerier::new(&[]),
+            entries,
+        },
+    };
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 2)],
+    )
+    .unwrap();
+
+    let nft_info = |token_id: u64, include_staking: bool| {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::InternNftInfo {
+                token_id: TokenId::new(token_id),
+                include_staking,
+            },
+        )
+        .unwrap()
+    };
+
+    // the plain response is unchanged
+    let token: InternNftInfoResponse = from_binary(&nft_info(1, false)).unwrap();
+    assert_eq!(token.name, "intern #1");
+
+    let res: InternTokenStakingInfo = from_binary(&nft_info(1, true)).unwrap();
+    assert_eq!(res.token, token);
+    assert_eq!(res.staking, Some(staking_info.clone()));
+
+    // tokens that were never staked have no staking record
+    let res: InternTokenStakingInfo = from_binary(&nft_info(2, true)).unwrap();
+    assert_eq!(res.token.name, "intern #2");
+    assert_eq!(res.staking, None);
+
+    let res: InternTokensStakingResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::InternTokens {
+                owner: NONOWNER.to_string(),
+                start_after: None,
+                start_before: None,
+                limit: None,
+                order: None,
+                include_staking: true,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let staking: Vec<_> = res.tokens.into_iter().map(|t| t.staking).collect();
+    assert_eq!(staking, vec![Some(staking_info), None]);
+}
+
+#[test]
+fn adjust_traits() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+
+    // only the staking contract can adjust traits
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        TokenId::new(1),
+        10,
+        5,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(1),
+        10,
+        5,
+        -20,
+    )
+    .unwrap();
+    let traits: InternExtension = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(traits.experience, 10);
+    assert_eq!(traits.gold, 5);
+    assert_eq!(traits.stamina, 80);
+
+    // deltas compose with the stored values
+    let res = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(1),
+        3,
+        -5,
+        0,
+    )
+    .unwrap();
+    let traits: InternExtension = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(traits.experience, 13);
+    assert_eq!(traits.gold, 0);
+    assert_eq!(traits.stamina, 80);
+
+    // an underflowing delta rejects the whole adjustment
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(1),
+        1,
+        -1,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TraitOutOfRange {
+            name: "gold".to_string()
+        }
+    );
+    let info = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+    assert_eq!(info.extension.experience, 13);
+}
+
+#[test]
+fn trait_updaters() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+
+    // only the owner can grant permissions
+    let err = ExecHandler::execute_grant_trait_updater(
+        deps.as_mut(),
+        mock_info(NONOWNER, &[]),
+        "shop".to_string(),
+        vec![TraitKind::Gold],
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = ExecHandler::execute_grant_trait_updater(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "shop".to_string(),
+        vec![],
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoTraitsGranted {});
+
+    // unregistered contracts can't touch traits
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        0,
+        5,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let _ = ExecHandler::execute_grant_trait_updater(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "shop".to_string(),
+        vec![TraitKind::Gold, TraitKind::Gold],
+    )
+    .unwrap();
+    let _ = ExecHandler::execute_grant_trait_updater(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "quest".to_string(),
+        vec![TraitKind::Stamina, TraitKind::Experience],
+    )
+    .unwrap();
+
+    let res = QueryHandler::query_trait_updaters(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        res.updaters,
+        vec![
+            TraitUpdater {
+                address: "quest".to_string(),
+                traits: vec![TraitKind::Experience, TraitKind::Stamina],
+            },
+            TraitUpdater {
+                address: "shop".to_string(),
+                traits: vec![TraitKind::Gold],
+            },
+        ]
+    );
+    let res =
+        QueryHandler::query_trait_updaters(deps.as_ref(), Some("quest".to_string()), None).unwrap();
+    assert_eq!(res.updaters.len(), 1);
+    assert_eq!(res.updaters[0].address, "shop");
+
+    // the shop may only change gold
+    let res = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        0,
+        5,
+        0,
+    )
+    .unwrap();
+    let traits: InternExtension = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(traits.gold, 5);
+
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        1,
+        -5,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TraitNotPermitted {
+            name: "experience".to_string()
+        }
+    );
+
+    // absolute updates are checked against the traits they actually change
+    let _ = ExecHandler::execute_update_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        0,
+        1,
+        100,
+    )
+    .unwrap();
+    let err = ExecHandler::execute_update_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        0,
+        1,
+        50,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TraitNotPermitted {
+            name: "stamina".to_string()
+        }
+    );
+
+    // the staking contract keeps full access
+    let _ = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(1),
+        1,
+        1,
+        1,
+    )
+    .unwrap();
+
+    let _ = ExecHandler::execute_revoke_trait_updater(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "shop".to_string(),
+    )
+    .unwrap();
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("shop", &[]),
+        TokenId::new(1),
+        0,
+        1,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = QueryHandler::query_trait_updaters(deps.as_ref(), None, None).unwrap();
+    assert_eq!(res.updaters.len(), 1);
+}
+
+fn set_traits(deps: DepsMut, token_id: u64, exp: u64, gold: u64) {
+    ExecHandler::execute_update_traits(
+        deps,
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(token_id),
+        exp,
+        gold,
+        100,
+    )
+    .unwrap();
+}
+
+fn leaderboard_names(res: InternTokensResponse) -> Vec<String> {
+    res.tokens.into_iter().map(|token| token.name).collect()
+}
+
+#[test]
+fn leaderboards() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 3)],
+    )
+    .unwrap();
+
+    set_traits(deps.as_mut(), 1, 300, 5);
+    set_traits(deps.as_mut(), 2, 100, 50);
+    set_traits(deps.as_mut(), 3, 200, 50);
+
+    let top_exp = |deps: Deps, start_after: Option<u64>, limit: Option<u32>| {
+        leaderboard_names(
+            QueryHandler::query_top_by_experience(deps, start_after.map(TokenId::new), limit)
+                .unwrap(),
+        )
+    };
+    assert_eq!(
+        top_exp(deps.as_ref(), None, None),
+        vec!["intern #1", "intern #3", "intern #2"]
+    );
+    // ties are ordered by token id, from the highest down
+    let top_gold = QueryHandler::query_top_by_gold(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        leaderboard_names(top_gold),
+        vec!["intern #3", "intern #2", "intern #1"]
+    );
+
+    // pages continue after the given token
+    assert_eq!(top_exp(deps.as_ref(), None, Some(1)), vec!["intern #1"]);
+    assert_eq!(top_exp(deps.as_ref(), Some(1), Some(1)), vec!["intern #3"]);
+    assert_eq!(top_exp(deps.as_ref(), Some(2), None), Vec::<String>::new());
+
+    // replacing a token moves it without leaving its old entries behind
+    set_traits(deps.as_mut(), 2, 400, 0);
+    assert_eq!(
+        top_exp(deps.as_ref(), None, None),
+        vec!["intern #2", "intern #1", "intern #3"]
+    );
+    let top_gold = QueryHandler::query_top_by_gold(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        leaderboard_names(top_gold),
+        vec!["intern #3", "intern #1", "intern #2"]
+    );
+
+    // burned tokens leave the leaderboards
+    let _ = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        TokenId::new(2),
+    )
+    .unwrap();
+    assert_eq!(
+        top_exp(deps.as_ref(), None, None),
+        vec!["intern #1", "intern #3"]
+    );
+}
+
+#[test]
+fn leaderboard_ties_are_ordered_by_number() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 12)],
+    )
+    .unwrap();
+    for token_id in 1..=12 {
+        set_traits(deps.as_mut(), token_id, 0, 10);
+    }
+
+    let top_gold = QueryHandler::query_top_by_gold(deps.as_ref(), None, Some(4)).unwrap();
+    assert_eq!(
+        leaderboard_names(top_gold),
+        vec!["intern #12", "intern #11", "intern #10", "intern #9"]
+    );
+    let top_gold =
+        QueryHandler::query_top_by_gold(deps.as_ref(), Some(TokenId::new(10)), Some(2)).unwrap();
+    assert_eq!(leaderboard_names(top_gold), vec!["intern #9", "intern #8"]);
+}
+
+#[test]
+fn search_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 3), (NONOWNER2.to_string(), 1)],
+    )
+    .unwrap();
+    set_traits(deps.as_mut(), 1, 100, 10);
+    set_traits(deps.as_mut(), 2, 200, 0);
+    set_traits(deps.as_mut(), 3, 300, 30);
+    set_traits(deps.as_mut(), 4, 400, 40);
+
+    let search = |owner: Option<&str>, filter: TraitFilter, start_after: Option<u64>, limit| {
+        let res = QueryHandler::query_search_tokens(
+            deps.as_ref(),
+            owner.map(String::from),
+            filter,
+            start_after.map(TokenId::new),
+            limit,
+        )
+        .unwrap();
+        leaderboard_names(res)
+    };
+
+    // no filters lists every token
+    assert_eq!(
+        search(None, TraitFilter::default(), None, None),
+        vec!["intern #1", "intern #2", "intern #3", "intern #4"]
+    );
+
+    let filter = TraitFilter {
+        min_experience: Some(150),
+        max_experience: Some(350),
+        ..TraitFilter::default()
+    };
+    assert_eq!(
+        search(None, filter.clone(), None, None),
+        vec!["intern #2", "intern #3"]
+    );
+    // pages skip non-matching tokens
+    assert_eq!(
+        search(None, filter.clone(), None, Some(1)),
+        vec!["intern #2"]
+    );
+    assert_eq!(search(None, filter, Some(2), Some(1)), vec!["intern #3"]);
+
+    let filter = TraitFilter {
+        min_gold: Some(10),
+        ..TraitFilter::default()
+    };
+    assert_eq!(
+        search(Some(NONOWNER), filter.clone(), None, None),
+        vec!["intern #1", "intern #3"]
+    );
+    assert_eq!(
+        search(Some(NONOWNER2), filter, None, None),
+        vec!["intern #4"]
+    );
+
+    let filter = TraitFilter {
+        min_stamina: Some(101),
+        ..TraitFilter::default()
+    };
+    assert_eq!(search(None, filter, None, None), Vec::<String>::new());
+}
+
+#[test]
+fn search_tokens_scan_limit() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 305)],
+    )
+    .unwrap();
+    set_traits(deps.as_mut(), 1, 500, 10);
+    set_traits(deps.as_mut(), 304, 200, 10);
+
+    let search = |filter: TraitFilter, start_after: Option<u64>, limit| {
+        QueryHandler::query_search_tokens(
+            deps.as_ref(),
+            None,
+            filter,
+            start_after.map(TokenId::new),
+            limit,
+        )
+        .unwrap()
+    };
+
+    // a call reads at most 300 tokens, and continues after the last one it read
+    let filter = TraitFilter {
+        min_gold: Some(10),
+        ..TraitFilter::default()
+    };
+    let res = search(filter.clone(), None, None);
+    assert_eq!(res.next_cursor, Some(TokenId::new(300)));
+    assert_eq!(leaderboard_names(res), vec!["intern #1"]);
+    let res = search(filter, Some(300), None);
+    assert_eq!(res.next_cursor, None);
+    assert_eq!(leaderboard_names(res), vec!["intern #304"]);
+
+    // experience bounds only read the tokens within them, by experience
+    let filter = TraitFilter {
+        min_experience: Some(100),
+        ..TraitFilter::default()
+    };
+    let res = search(filter.clone(), None, None);
+    assert_eq!(res.next_cursor, None);
+    assert_eq!(leaderboard_names(res), vec!["intern #304", "intern #1"]);
+    let res = search(filter.clone(), None, Some(1));
+    assert_eq!(res.next_cursor, Some(TokenId::new(304)));
+    assert_eq!(leaderboard_names(res), vec!["intern #304"]);
+    let res = search(filter, Some(304), Some(1));
+    assert_eq!(res.next_cursor, None);
+    assert_eq!(leaderboard_names(res), vec!["intern #1"]);
+    let filter = TraitFilter {
+        min_experience: Some(100),
+        max_experience: Some(499),
+        ..TraitFilter::default()
+    };
+    assert_eq!(
+        leaderboard_names(search(filter, None, None)),
+        vec!["intern #304"]
+    );
+}
+
+fn migrate_from(deps: DepsMut, version: &str, msg: MigrateMsg) -> StdResult<Response> {
+    set_contract_version(deps.storage, "crates.io:internnft-nft-contract", version).unwrap();
+    migrate(deps, mock_env(), msg)
+}
+
+// runs the batched migration steps until they are done
+fn finish_migration(mut deps: DepsMut) {
+    loop {
+        let res =
+            ExecHandler::execute_resume_migration(deps.branch(), mock_info(OWNER, &[]), Some(1))
+                .unwrap();
+        if res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "done" && attr.value == "true")
+        {
+            return;
+        }
+    }
+}
+
+#[test]
+fn migrate_versions() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    // other contracts and newer versions can't be migrated from
+    set_contract_version(deps.as_mut().storage, "crates.io:other-contract", "0.1.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("can't migrate to contract with different name")
+    );
+    let err = migrate_from(deps.as_mut(), "99.0.0", MigrateMsg::default()).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("can't migrate to an older contract version")
+    );
+
+    // the upgrades newer than the stored version run, and the new version is stored
+    let res = migrate_from(deps.as_mut(), "0.2.2", MigrateMsg::default()).unwrap();
+    assert_eq!(
+        res.attributes[2],
+        (
+            "migrations",
+            "upgrade_config,backfill_token_indexes,seal_import"
+        )
+    );
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+    assert_eq!(res.attributes[2], ("migrations", ""));
+    assert_eq!(
+        QueryHandler::query_config(deps.as_ref()).unwrap(),
+        mock_config()
+    );
+
+    // the new settings can be set while migrating
+    let starting_traits = TraitRanges {
+        stamina: TraitRange { min: 50, max: 100 },
+        ..TraitRanges::default()
+    };
+    let metadata = MetadataConfig {
+        base_uri: Some("ipfs://cid/".to_string()),
+        ..MetadataConfig::default()
+    };
+    let _ = migrate_from(
+        deps.as_mut(),
+        "0.2.2",
+        MigrateMsg {
+            starting_traits: Some(starting_traits),
+            burn_hook: Some("burn_hook".to_string()),
+            metadata: Some(metadata.clone()),
+        },
+    )
+    .unwrap();
+    let config = QueryHandler::query_config(deps.as_ref()).unwrap();
+    assert_eq!(config.starting_traits, starting_traits);
+    assert_eq!(config.burn_hook, Some("burn_hook".to_string()));
+    assert_eq!(
+        QueryHandler::query_metadata_config(deps.as_ref()).unwrap(),
+        metadata
+    );
+
+    // and are validated like with UpdateConfig
+    let err = migrate_from(
+        deps.as_mut(),
+        "0.2.2",
+        MigrateMsg {
+            starting_traits: Some(TraitRanges {
+                stamina: TraitRange { min: 10, max: 1 },
+                ..TraitRanges::default()
+            }),
+            ..MigrateMsg::default()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, StdError::GenericErr { .. }));
+}
+
+#[test]
+fn migrate_indexes_existing_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+
+    // a token saved before the leaderboard indexes existed
+    let mut token = tokens().load(deps.as_ref().storage, "intern #1").unwrap();
+    token.name = "intern #2".to_string();
+    token.extension.experience = 500;
+    Map::<&str, InternTokenInfo>::new("tokens")
+        .save(deps.as_mut().storage, "intern #2", &token)
+        .unwrap();
+    let top = QueryHandler::query_top_by_experience(deps.as_ref(), None, None).unwrap();
+    assert_eq!(leaderboard_names(top), vec!["intern #1"]);
+
+    migrate_from(deps.as_mut(), "0.2.2", MigrateMsg::default()).unwrap();
+
+    // tokens can't move until the backfill is done
+    let err = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER2.to_string(),
+            token_id: TokenId::new(1),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationPending {});
+    let err = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationPending {});
+
+    // only the owner can run it
+    let err = ExecHandler::execute_resume_migration(deps.as_mut(), mock_info(NONOWNER, &[]), None)
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = ExecHandler::execute_resume_migration(deps.as_mut(), mock_info(OWNER, &[]), Some(1))
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "resume_migration")
+            .add_attribute("processed", "1")
+            .add_attribute("done", "false")
+    );
+    let res = ExecHandler::execute_resume_migration(deps.as_mut(), mock_info(OWNER, &[]), Some(1))
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "resume_migration")
+            .add_attribute("processed", "1")
+            .add_attribute("done", "true")
+    );
+
+    let top = QueryHandler::query_top_by_experience(deps.as_ref(), None, None).unwrap();
+    assert_eq!(leaderboard_names(top), vec!["intern #2", "intern #1"]);
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 2);
+    let tokens = QueryHandler::query_intern_tokens(
+        deps.as_ref(),
+        NONOWNER.to_string(),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(tokens.tokens.len(), 2);
+
+    // a deployment with tokens can't be seeded anymore
+    let err =
+        ExecHandler::execute_import_tokens(deps.as_mut(), mock_info(OWNER, &[]), vec![], None)
+            .unwrap_err();
+    assert_eq!(err, ContractError::ImportSealed {});
+}
+
+fn num_tokens(deps: Deps, owner: &str) -> u64 {
+    QueryHandler::query_num_tokens_for_owner(deps, owner.to_string())
+        .unwrap()
+        .count
+}
+
+fn owned_tokens(deps: Deps, owner: &str) -> Vec<String> {
+    QueryHandler::query_tokens(deps, owner.to_string(), None, None, None, None)
+        .unwrap()
+        .tokens
+}
+
+#[test]
+fn owner_token_counts() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 2), (NONOWNER2.to_string(), 1)],
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 2);
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER2), 1);
+
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER2.to_string(),
+            token_id: TokenId::new(1),
+        },
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 1);
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER2), 2);
+    // so do the owners' token lists
+    assert_eq!(owned_tokens(deps.as_ref(), NONOWNER), vec!["2"]);
+    assert_eq!(owned_tokens(deps.as_ref(), NONOWNER2), vec!["1", "3"]);
+
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::SendNft {
+            contract: "staking_contract".to_string(),
+            token_id: TokenId::new(2),
+            msg: Binary::default(),
+        },
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 0);
+    assert_eq!(num_tokens(deps.as_ref(), "staking_contract"), 1);
+    assert!(owned_tokens(deps.as_ref(), NONOWNER).is_empty());
+    assert_eq!(owned_tokens(deps.as_ref(), "staking_contract"), vec!["2"]);
+
+    // failed transfers leave the counters alone
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER.to_string(),
+            token_id: TokenId::new(3),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER2), 2);
+
+    let _ = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        TokenId::new(3),
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER2), 1);
+    assert_eq!(owned_tokens(deps.as_ref(), NONOWNER2), vec!["1"]);
+
+    // the migration rebuilds the counters from the tokens
+    OWNER_TOKEN_COUNTS
+        .save(deps.as_mut().storage, &Addr::unchecked(NONOWNER), &7)
+        .unwrap();
+    OWNER_TOKEN_COUNTS.remove(deps.as_mut().storage, &Addr::unchecked(NONOWNER2));
+    migrate_from(deps.as_mut(), "0.2.2", MigrateMsg::default()).unwrap();
+    finish_migration(deps.as_mut());
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 0);
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER2), 1);
+    assert_eq!(num_tokens(deps.as_ref(), "staking_contract"), 1);
+}
+
+#[test]
+fn transfer_wallet_limit() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, Some(1));
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![
+            (NONOWNER.to_string(), 1),
+            (NONOWNER2.to_string(), 2),
+            ("marketplace".to_string(), 1),
+        ],
+    )
+    .unwrap();
+
+    // recipients can't go over the wallet limit
+    let err = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER2.to_string(),
+            token_id: TokenId::new(1),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimit {});
+    let send_to_marketplace = ExecuteMsg::SendNft {
+        contract: "marketplace".to_string(),
+        token_id: TokenId::new(1),
+        msg: Binary::default(),
+    };
+    let err = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        send_to_marketplace.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimit {});
+
+    // only the owner can manage exemptions
+    let err = ExecHandler::execute_add_wallet_limit_exemption(
+        deps.as_mut(),
+        mock_info(NONOWNER, &[]),
+        "marketplace".to_string(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let _ = ExecHandler::execute_add_wallet_limit_exemption(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "marketplace".to_string(),
+    )
+    .unwrap();
+    let res = QueryHandler::query_wallet_limit_exemptions(deps.as_ref(), None, None).unwrap();
+    assert_eq!(res.addresses, vec!["marketplace".to_string()]);
+
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        send_to_marketplace,
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), "marketplace"), 2);
+
+    // the staking contract is always exempt
+    for (sender, token_id) in [(NONOWNER2, 2), ("marketplace", 4)].iter() {
+        let _ = ExecHandler::cw721_base_execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::SendNft {
+                contract: "staking_contract".to_string(),
+                token_id: TokenId::new(*token_id),
+                msg: Binary::default(),
+            },
+        )
+        .unwrap();
+    }
+    assert_eq!(num_tokens(deps.as_ref(), "staking_contract"), 2);
+
+    let err = ExecHandler::execute_remove_wallet_limit_exemption(
+        deps.as_mut(),
+        mock_info(NONOWNER, &[]),
+        "marketplace".to_string(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let _ = ExecHandler::execute_remove_wallet_limit_exemption(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "marketplace".to_string(),
+    )
+    .unwrap();
+    let res = QueryHandler::query_wallet_limit_exemptions(deps.as_ref(), None, None).unwrap();
+    assert!(res.addresses.is_empty());
+
+    // the marketplace holds one token again, so it is full
+    let err = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        ExecuteMsg::TransferNft {
+            recipient: "marketplace".to_string(),
+            token_id: TokenId::new(3),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimit {});
+}
+
+#[test]
+fn unstake_at_wallet_limit() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, Some(1));
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::SendNft {
+            contract: "staking_contract".to_string(),
+            token_id: TokenId::new(1),
+            msg: Binary::default(),
+        },
+    )
+    .unwrap();
+
+    // the staker fills their wallet while the token is staked
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 1);
+
+    // but can still withdraw it
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER.to_string(),
+            token_id: TokenId::new(1),
+        },
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 2);
+
+    // tokens from exempted addresses aren't limited either
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![("marketplace".to_string(), 1)],
+    )
+    .unwrap();
+    let transfer_from_marketplace = ExecuteMsg::TransferNft {
+        recipient: NONOWNER.to_string(),
+        token_id: TokenId::new(3),
+    };
+    let err = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("marketplace", &[]),
+        transfer_from_marketplace.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimit {});
+    let _ = ExecHandler::execute_add_wallet_limit_exemption(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        "marketplace".to_string(),
+    )
+    .unwrap();
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("marketplace", &[]),
+        transfer_from_marketplace,
+    )
+    .unwrap();
+    assert_eq!(num_tokens(deps.as_ref(), NONOWNER), 3);
+}
+
+fn export_all(deps: Deps) -> (u64, Vec<ExportedToken>) {
+    let mut exported = vec![];
+    let mut start_after = None;
+    loop {
+        let page = QueryHandler::query_export_state(deps, start_after, Some(2)).unwrap();
+        exported.extend(page.tokens);
+        match page.next_cursor {
+            Some(cursor) => start_after = Some(cursor),
+            None => return (page.minted, exported),
+        }
+    }
+}
+
+#[test]
+fn export_import_state() {
+    let mut old = mock_dependencies(&[]);
+    setup_contract(old.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        old.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 3), (NONOWNER2.to_string(), 1)],
+    )
+    .unwrap();
+    set_traits(old.as_mut(), 2, 40, 400);
+    let _ = ExecHandler::cw721_base_execute(
+        old.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::Approve {
+            spender: NONOWNER2.to_string(),
+            token_id: TokenId::new(1),
+            expires: None,
+        },
+    )
+    .unwrap();
+    let _ = ExecHandler::execute_burn(
+        old.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER2, &[]),
+        TokenId::new(4),
+    )
+    .unwrap();
+
+    let (minted, exported) = export_all(old.as_ref());
+    assert_eq!(minted, 4);
+    let ids: Vec<TokenId> = exported.iter().map(|token| token.token_id).collect();
+    assert_eq!(ids, vec![TokenId::new(1), TokenId::new(2), TokenId::new(3)]);
+    assert_eq!(exported[0].token.approvals.len(), 1);
+    assert_eq!(exported[1].token.extension.experience, 40);
+
+    // only the owner can import
+    let mut new = mock_dependencies(&[]);
+    setup_contract(new.as_mut(), None, None, None);
+    let err = ExecHandler::execute_import_tokens(
+        new.as_mut(),
+        mock_info(NONOWNER, &[]),
+        exported.clone(),
+        Some(minted),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // tokens are imported exactly, in as many batches as needed
+    let _ = ExecHandler::execute_import_tokens(
+        new.as_mut(),
+        mock_info(OWNER, &[]),
+        exported[..2].to_vec(),
+        Some(minted),
+    )
+    .unwrap();
+    let _ = ExecHandler::execute_import_tokens(
+        new.as_mut(),
+        mock_info(OWNER, &[]),
+        exported[2..].to_vec(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(export_all(new.as_ref()), (minted, exported.clone()));
+    assert_eq!(num_tokens(new.as_ref(), NONOWNER), 3);
+    let count = Cw721Contract::<InternExtension, Empty>::default()
+        .token_count(new.as_ref().storage)
+        .unwrap();
+    assert_eq!(count, 3);
+    let top = QueryHandler::query_top_by_experience(new.as_ref(), None, Some(1)).unwrap();
+    assert_eq!(leaderboard_names(top), vec!["intern #2"]);
+
+    // ids can't be imported twice, and names must match the ids
+    let err = ExecHandler::execute_import_tokens(
+        new.as_mut(),
+        mock_info(OWNER, &[]),
+        exported[..1].to_vec(),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Claimed {});
+    let mut renamed = exported[0].clone();
+    renamed.token_id = TokenId::new(9);
+    let err = ExecHandler::execute_import_tokens(
+        new.as_mut(),
+        mock_info(OWNER, &[]),
+        vec![renamed],
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("token 9 must be named 'intern #9'"))
+    );
+
+    // nor can they go past the token supply
+    let mut beyond_supply = exported[0].clone();
+    beyond_supply.token_id = TokenId::new(10_001);
+    beyond_supply.token.name = "intern #10001".to_string();
+    for (imported, minted) in [(vec![], Some(10_001)), (vec![beyond_supply], None)].iter() {
+        let err = ExecHandler::execute_import_tokens(
+            new.as_mut(),
+            mock_info(OWNER, &[]),
+            imported.clone(),
+            *minted,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ImportExceedsSupply {
+                token_supply: 10_000
+            }
+        );
+    }
+
+    // owners and approved spenders must be valid addresses
+    let mut invalid_owner = exported[0].clone();
+    invalid_owner.token_id = TokenId::new(9);
+    invalid_owner.token.name = "intern #9".to_string();
+    invalid_owner.token.owner = Addr::unchecked("x");
+    let mut invalid_spender = invalid_owner.clone();
+    invalid_spender.token.owner = Addr::unchecked(NONOWNER);
+    invalid_spender.token.approvals[0].spender = Addr::unchecked("x");
+    for imported in [invalid_owner, invalid_spender].iter() {
+        let err = ExecHandler::execute_import_tokens(
+            new.as_mut(),
+            mock_info(OWNER, &[]),
+            vec![imported.clone()],
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+    }
+
+    // sealing ends the import for good
+    let err = ExecHandler::execute_seal_import(new.as_mut(), mock_info(NONOWNER, &[])).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let _ = ExecHandler::execute_seal_import(new.as_mut(), mock_info(OWNER, &[])).unwrap();
+    let err = ExecHandler::execute_import_tokens(new.as_mut(), mock_info(OWNER, &[]), vec![], None)
+        .unwrap_err();
+    assert_eq!(err, ContractError::ImportSealed {});
+    assert!(
+        QueryHandler::query_export_state(new.as_ref(), None, None)
+            .unwrap()
+            .import_sealed
+    );
+
+    // new mints continue after the imported counter
+    let _ = ExecHandler::execute_batch_mint(
+        new.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER2.to_string(), 1)],
+    )
+    .unwrap();
+    let token = QueryHandler::query_intern_nft_info(new.as_ref(), TokenId::new(5)).unwrap();
+    assert_eq!(token.owner, NONOWNER2);
+}
+
+#[test]
+fn mint_seals_import() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let err =
+        ExecHandler::execute_import_tokens(deps.as_mut(), mock_info(OWNER, &[]), vec![], None)
+            .unwrap_err();
+    assert_eq!(err, ContractError::ImportSealed {});
+}
+
+fn pause(deps: DepsMut, flags: PauseFlags) {
+    let _ = ExecHandler::execute_update_pause_flags(deps, mock_info(OWNER, &[]), flags).unwrap();
+}
+
+#[test]
+fn pause_flags() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 2)],
+    )
+    .unwrap();
+
+    // only the owner can pause
+    let flags = PauseFlags {
+        minting: true,
+        ..PauseFlags::default()
+    };
+    let err =
+        ExecHandler::execute_update_pause_flags(deps.as_mut(), mock_info(NONOWNER, &[]), flags)
+            .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = ExecHandler::execute_update_pause_flags(deps.as_mut(), mock_info(OWNER, &[]), flags)
+        .unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(res.events[0].ty, "pause_status");
+    assert_eq!(
+        res.events[0].attributes,
+        vec![
+            ("minting", "true"),
+            ("transfers", "false"),
+            ("trait_updates", "false")
+        ]
+    );
+    let status: PauseFlags =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap()).unwrap();
+    assert_eq!(status, flags);
+
+    // each flag halts its own operations only
+    let err = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MintingPaused {});
+    let err = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MintingPaused {});
+    set_traits(deps.as_mut(), 1, 10, 10);
+    let transfer = ExecuteMsg::TransferNft {
+        recipient: NONOWNER2.to_string(),
+        token_id: TokenId::new(1),
+    };
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        transfer,
+    )
+    .unwrap();
+    let _ = ExecHandler::cw721_base_execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        ExecuteMsg::Approve {
+            spender: NONOWNER2.to_string(),
+            token_id: TokenId::new(2),
+            expires: None,
+        },
+    )
+    .unwrap();
+
+    pause(
+        deps.as_mut(),
+        PauseFlags {
+            transfers: true,
+            ..PauseFlags::default()
+        },
+    );
+    for msg in [
+        ExecuteMsg::TransferNft {
+            recipient: NONOWNER2.to_string(),
+            token_id: TokenId::new(2),
+        },
+        ExecuteMsg::SendNft {
+            contract: "staking_contract".to_string(),
+            token_id: TokenId::new(2),
+            msg: Binary::default(),
+        },
+        ExecuteMsg::Approve {
+            spender: NONOWNER2.to_string(),
+            token_id: TokenId::new(2),
+            expires: None,
+        },
+    ]
+    .iter()
+    {
+        let err = ExecHandler::cw721_base_execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(NONOWNER, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TransfersPaused {});
+    }
+    let err = ExecHandler::execute_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        TokenId::new(2),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TransfersPaused {});
+    // approvals can still be taken back
+    for msg in [
+        ExecuteMsg::Revoke {
+            spender: NONOWNER2.to_string(),
+            token_id: TokenId::new(2),
+        },
+        ExecuteMsg::RevokeAll {
+            operator: NONOWNER2.to_string(),
+        },
+    ]
+    .iter()
+    {
+        let _ = ExecHandler::cw721_base_execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(NONOWNER, &[]),
+            msg.clone(),
+        )
+        .unwrap();
+    }
+    let token = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(2)).unwrap();
+    assert!(token.approvals.is_empty());
+    let _ = ExecHandler::execute_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        None,
+        None,
+    )
+    .unwrap();
+
+    pause(
+        deps.as_mut(),
+        PauseFlags {
+            trait_updates: true,
+            ..PauseFlags::default()
+        },
+    );
+    let err = ExecHandler::execute_update_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(2),
+        1,
+        1,
+        1,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TraitUpdatesPaused {});
+    let err = ExecHandler::execute_adjust_traits(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking_contract", &[]),
+        TokenId::new(2),
+        1,
+        0,
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TraitUpdatesPaused {});
+
+    // queries keep working
+    let token = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+    assert_eq!(token.owner, NONOWNER2);
+    assert_eq!(token.extension.experience, 10);
+
+    pause(deps.as_mut(), PauseFlags::default());
+    set_traits(deps.as_mut(), 2, 20, 20);
+}
+
+#[test]
+fn metadata_config() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let _ = ExecHandler::execute_batch_mint(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![(NONOWNER.to_string(), 1)],
+    )
+    .unwrap();
+
+    // nothing is filled in by default
+    let info = QueryHandler::query_nft_info(deps.as_ref(), mock_env(), TokenId::new(1)).unwrap();
+    assert_eq!(info.token_uri, None);
+    assert_eq!(info.extension.external_url, None);
+    assert_eq!(info.extension.background_color, None);
+
+    let metadata = MetadataConfig {
+        base_uri: Some("ipfs://cid/".to_string()),
+        external_url: Some("https://interns.example/{id}".to_string()),
+        background_color: Some("1b1f3b".to_string()),
+    };
+
+    // only the owner can update the settings
+    let err = ExecHandler::execute_update_metadata_config(
+        deps.as_mut(),
+        mock_info(NONOWNER, &[]),
+        metadata.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = ExecHandler::execute_update_metadata_config(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        MetadataConfig {
+            background_color: Some("#1b1f3b".to_string()),
+            ..metadata.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "invalid background color: expected six hex digits"
+        ))
+    );
+
+    let _ = ExecHandler::execute_update_metadata_config(
+        deps.as_mut(),
+        mock_info(OWNER, &[]),
+        metadata.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        QueryHandler::query_metadata_config(deps.as_ref()).unwrap(),
+        metadata
+    );
+
+    let info = QueryHandler::query_nft_info(deps.as_ref(), mock_env(), TokenId::new(1)).unwrap();
+    assert_eq!(info.token_uri, Some("ipfs://cid/1".to_string()));
+    assert_eq!(
+        info.extension.external_url,
+        Some("https://interns.example/1".to_string())
+    );
+    assert_eq!(info.extension.background_color, Some("1b1f3b".to_string()));
+
+    let all_info =
+        QueryHandler::query_all_nft_info(deps.as_ref(), mock_env(), TokenId::new(1), false)
+            .unwrap();
+    assert_eq!(all_info.info, info);
+
+    let intern = QueryHandler::query_intern_nft_info(deps.as_ref(), TokenId::new(1)).unwrap();
+    assert_eq!(intern.token_uri, Some("ipfs://cid/1".to_string()));
+    assert_eq!(
+        intern.external_url,
+        Some("https://interns.example/1".to_string())
+    );
+    assert_eq!(intern.background_color, Some("1b1f3b".to_string()));
+
+    // lists fill in the same settings
+    let all = QueryHandler::query_all_intern_tokens(deps.as_ref(), None, None, None, None).unwrap();
+    assert_eq!(all.tokens, vec![intern.clone()]);
+    let owned = QueryHandler::query_intern_tokens(
+        deps.as_ref(),
+        NONOWNER.to_string(),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(owned.tokens, vec![intern.clone()]);
+    let found =
+        QueryHandler::query_search_tokens(deps.as_ref(), None, TraitFilter::default(), None, None)
+            .unwrap();
+    assert_eq!(found.tokens, vec![intern]);
+}
+
+#[test]
+fn ownership_transfer() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    // nonowner can't propose a new owner
+    let err = ExecHandler::execute_propose_new_owner(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        NONOWNER.to_string(),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // nothing to accept yet
+    let err =
+        ExecHandler::execute_accept_ownership(deps.as_mut(), mock_env(), mock_info(NONOWNER, &[]))
+            .unwrap_err();
+    assert_eq!(err, ContractError::NoOwnershipProposal {});
+
+    let _ = ExecHandler::execute_propose_new_owner(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        NONOWNER.to_string(),
+        None,
+    )
+    .unwrap();
+    let res = QueryHandler::query_owner(deps.as_ref()).unwrap();
+    assert_eq!(
+        res,
+        OwnerResponse {
+            owner: OWNER.to_string(),
+            proposal: Some(OwnershipProposal {
+                owner: Addr::unchecked(NONOWNER),
+                expires: Expiration::Never {},
+            }),
+        }
+    );
+
+    // only the proposed owner can accept
+    let err =
+        ExecHandler::execute_accept_ownership(deps.as_mut(), mock_env(), mock_info(NONOWNER2, &[]))
+            .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let _ =
+        ExecHandler::execute_accept_ownership(deps.as_mut(), mock_env(), mock_info(NONOWNER, &[]))
+            .unwrap();
+    let res = QueryHandler::query_owner(deps.as_ref()).unwrap();
+    assert_eq!(
+        res,
+        OwnerResponse {
+            owner: NONOWNER.to_string(),
+            proposal: None,
+        }
+    );
+
+    // owner-only actions follow the new owner
+    let err =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(OWNER, &[]), mock_config())
+            .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let _ =
+        ExecHandler::execute_update_config(deps.as_mut(), mock_info(NONOWNER, &[]), mock_config())
+            .unwrap();
+
+    let err = ExecHandler::execute_withdraw(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        vec![Coin::new(100, "uluna")],
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = ExecHandler::execute_withdraw(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(NONOWNER, &[]),
+        vec![Coin::new(100, "uluna")],
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        BankMsg::Send {
+            amount: vec![Coin::new(100, "uluna")],
+            to_address: NONOWNER.to_string()
+        }
+        .into()
+    );
+}
+
+#[test]
+fn ownership_proposal_expiry_and_cancel() {
+    let mut deps = mock_dependencies(&[]);
+    setup_contract(deps.as_mut(), None, None, None);
+
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+
+    // proposals can't be born expired
+    let err = ExecHandler::execute_propose_new_owner(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        NONOWNER.to_string(),
+        Some(Expiration::AtHeight(mock_env().block.height)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OwnershipProposalExpired {});
+
+    let _ = ExecHandler::execute_propose_new_owner(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        NONOWNER.to_string(),
+        Some(expires),
+    )
+    .unwrap();
+
+    // expired proposals can't be accepted
+    let mut env = mock_env();
+    env.block.height += 10;
+    let err = ExecHandler::execute_accept_ownership(deps.as_mut(), env, mock_info(NONOWNER, &[]))
+        .unwrap_err();
+    assert_eq!(err, ContractError::OwnershipProposalExpired {});
+
+    // only the owner can cancel
+    let err =
+        ExecHandler::execute_cancel_ownership_proposal(deps.as_mut(), mock_info(NONOWNER, &[]))
+            .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let _ = ExecHandler::execute_cancel_ownership_proposal(deps.as_mut(), mock_info(OWNER, &[]))
+        .unwrap();
+    let err =
+        ExecHandler::execute_accept_ownership(deps.as_mut(), mock_env(), mock_info(NONOWNER, &[]))
+            .unwrap_err();
+    assert_eq!(err, ContractError::NoOwnershipProposal {});
+
+    let err = ExecHandler::execute_cancel_ownership_proposal(deps.as_mut(), mock_info(OWNER, &[]))
+        .unwrap_err();
+    assert_eq!(err, ContractError::NoOwnershipProposal {});
+
+    let res = QueryHandler::query_owner(deps.as_ref()).unwrap();
+    assert_eq!(res.owner, OWNER.to_string());
+}
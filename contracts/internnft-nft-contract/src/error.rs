@@ -1,5 +1,6 @@
 use cosmwasm_std::StdError;
 use cw721_base::ContractError as CW721ContractError;
+use internnft::nft::UnsupportedMessage;
 use thiserror::Error;
 
 /// This overrides the ContractError enum defined in cw721-base
@@ -17,14 +18,86 @@ pub enum ContractError {
     #[error("Cannot set approval that is already expired")]
     Expired {},
 
+    #[error("No pending ownership proposal")]
+    NoOwnershipProposal {},
+
+    #[error("Ownership proposal expired")]
+    OwnershipProposalExpired {},
+
     #[error("Per-wallet token allotment exceeded")]
     WalletLimit {},
 
+    #[error("Token is staked")]
+    TokenStaked {},
+
+    #[error("Adjustment would take {name} out of range")]
+    TraitOutOfRange { name: String },
+
+    #[error("Not permitted to update {name}")]
+    TraitNotPermitted { name: String },
+
+    #[error("At least one trait must be granted")]
+    NoTraitsGranted {},
+
+    #[error("Token supply exhausted")]
+    SupplyExhausted {},
+
+    #[error("Cannot mint {requested} tokens, only {remaining} left in the supply")]
+    BatchExceedsSupply { requested: u64, remaining: u64 },
+
+    #[error("Invalid allowlist signature")]
+    InvalidSignature {},
+
+    #[error("Minting is restricted to allowlisted wallets")]
+    AllowlistOnly {},
+
+    #[error("Allowlist mint quota exhausted")]
+    AllowlistQuotaExceeded {},
+
+    #[error("Invalid allowlist merkle proof")]
+    InvalidMerkleProof {},
+
+    #[error("Minting is closed")]
+    MintClosed {},
+
+    #[error("Mint phase supply exhausted")]
+    PhaseSupplyExhausted {},
+
+    #[error("Per-wallet mint phase allotment exceeded")]
+    PhaseWalletLimit {},
+
     #[error("Move target out-of-bounds or already occupied")]
     InvalidMoveTarget {},
 
     #[error("Move already in progress")]
     MoveInProgress {},
+
+    #[error("Token import is sealed")]
+    ImportSealed {},
+
+    #[error("Import would exceed the token supply of {token_supply}")]
+    ImportExceedsSupply { token_supply: u64 },
+
+    #[error("Minting is paused")]
+    MintingPaused {},
+
+    #[error("Transfers are paused")]
+    TransfersPaused {},
+
+    #[error("Trait updates are paused")]
+    TraitUpdatesPaused {},
+
+    #[error("Migration in progress, run ResumeMigration until it is done")]
+    MigrationPending {},
+
+    #[error("Unsupported message: {msg}")]
+    UnsupportedMessage { msg: String },
+}
+
+impl From<UnsupportedMessage> for ContractError {
+    fn from(err: UnsupportedMessage) -> ContractError {
+        ContractError::UnsupportedMessage { msg: err.msg }
+    }
 }
 
 impl From<CW721ContractError> for ContractError {
@@ -1,79 +1,588 @@
+use std::convert::TryFrom;
+
 use cosmwasm_std::{
-    Attribute, BankMsg, Binary, Coin, DepsMut, Empty, Env, MessageInfo, Order, Response, StdError,
-    StdResult, Storage,
+    to_binary, Addr, Api, Attribute, BankMsg, Binary, Coin, DepsMut, Empty, Env, Event,
+    MessageInfo, Response, StdError, StdResult, Storage,
 };
-use cw721::{ContractInfoResponse, Cw721ReceiveMsg};
+use cw721::{ContractInfoResponse, Cw721ReceiveMsg, Expiration};
 use cw721_base::{msg::ExecuteMsg as Cw721ExecuteMsg, Cw721Contract};
+use internnft::migrations::{migration_batch_size, run_migrations};
 use internnft::nft::{
-    full_token_id, numeric_token_id, Config, ExecuteMsg, InstantiateMsg, InternExtension,
-    MigrateMsg,
+    full_token_id, numeric_token_id, validate_mint_phases, AllowlistProof, AllowlistSignature,
+    Config, ExecuteMsg, ExportedToken, InstantiateMsg, InternBurnedMsg, InternExtension,
+    InternTokenInfo, MetadataConfig, MigrateMsg, MintPhaseKind, OwnershipProposal, PauseFlags,
+    TraitKind,
 };
+use internnft::token_id::TokenId;
+use semver::Version;
 
+use crate::allowlist::{
+    parse_public_key, use_merkle_quota, use_signature, verify_merkle_proof, verify_signature,
+};
 use crate::error::ContractError;
-use crate::state::{tokens, CONFIG, OWNER};
-
-const INTERN: &str = "intern";
+use crate::migrations::{continue_index_backfill, MIGRATIONS};
+use crate::phases::use_phase_allocation;
+use crate::state::{
+    decrement_owner_tokens, import_sealed, increment_owner_tokens, minted_count, owner_token_count,
+    pause_flags, token_prefix, tokens, CONFIG, IMPORT_SEALED, INDEX_BACKFILL, METADATA_CONFIG,
+    MINTED, OWNER, OWNERSHIP_PROPOSAL, PAUSE_FLAGS, TOKEN_PREFIX, TRAIT_UPDATERS,
+    WALLET_LIMIT_EXEMPTIONS,
+};
+use crate::traits::{mint_seed, roll_traits};
 
 pub fn instantiate(deps: DepsMut, info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
     let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
 
+    if msg.name.is_empty() || msg.symbol.is_empty() {
+        return Err(StdError::generic_err("name and symbol must not be empty"));
+    }
+    // a trailing digit would make the numeric part of a token id ambiguous
+    match msg.token_prefix.chars().last() {
+        Some(c) if !c.is_ascii_digit() => {}
+        _ => {
+            return Err(StdError::generic_err(
+                "token prefix must not be empty or end with a digit",
+            ))
+        }
+    }
+
     let contract_info = ContractInfoResponse {
-        name: INTERN.to_string(),
-        symbol: INTERN.to_string(),
+        name: msg.name,
+        symbol: msg.symbol,
     };
     cw721_contract
         .contract_info
         .save(deps.storage, &contract_info)?;
+    TOKEN_PREFIX.save(deps.storage, &msg.token_prefix)?;
 
+    validate_config(deps.api, &msg.config)?;
+    msg.metadata.validate()?;
     CONFIG.save(deps.storage, &msg.config)?;
+    METADATA_CONFIG.save(deps.storage, &msg.metadata)?;
     OWNER.save(deps.storage, &info.sender.to_string())?;
 
     Ok(Response::default())
 }
 
+fn validate_config(api: &dyn Api, config: &Config) -> StdResult<()> {
+    config.starting_traits.validate()?;
+    // every burn would fail on dispatching the hook otherwise
+    if let Some(burn_hook) = &config.burn_hook {
+        api.addr_validate(burn_hook)?;
+    }
+    validate_mint_phases(&config.mint_phases)?;
+    if let Some(public_key) = &config.allowlist_public_key {
+        parse_public_key(public_key)?;
+    }
+    if let Some(root) = &config.allowlist_merkle_root {
+        if root.len() != 32 {
+            return Err(StdError::generic_err(
+                "invalid allowlist merkle root: expected a sha256 hash",
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn execute_mint(
-    _deps: DepsMut,
-    _env: Env,
+    deps: DepsMut,
+    env: Env,
     info: MessageInfo,
+    signature: Option<AllowlistSignature>,
+    proof: Option<AllowlistProof>,
 ) -> Result<Response, ContractError> {
-    //TODO: Write minting contract.
+    if pause_flags(deps.storage)?.minting {
+        return Err(ContractError::MintingPaused {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let owner = OWNER.load(deps.storage)?;
+
+    if minted_count(deps.storage)? >= config.token_supply {
+        return Err(ContractError::SupplyExhausted {});
+    }
+
+    let is_owner = info.sender == owner;
+    // the contract owner isn't bound by the sale schedule
+    let phase = if is_owner || config.mint_phases.is_empty() {
+        None
+    } else {
+        let active = config
+            .active_phase(&env.block)
+            .filter(|(_, phase)| phase.kind != MintPhaseKind::Closed)
+            .ok_or(ContractError::MintClosed {})?;
+        Some(active)
+    };
+    let (price, allowlist_only) = match phase {
+        Some((_, phase)) => (phase.price.clone(), phase.kind == MintPhaseKind::Allowlist),
+        None => (config.mint_fee.clone(), config.presale),
+    };
+
+    match (signature, proof) {
+        (Some(signature), None) => {
+            let public_key = config
+                .allowlist_public_key
+                .as_ref()
+                .ok_or(ContractError::InvalidSignature {})?;
+            verify_signature(public_key, &env, &info.sender, &signature)?;
+            use_signature(deps.storage, &info.sender, &signature)?;
+        }
+        (None, Some(proof)) => {
+            let root = config
+                .allowlist_merkle_root
+                .as_ref()
+                .ok_or(ContractError::InvalidMerkleProof {})?;
+            verify_merkle_proof(root, &info.sender, &proof)?;
+            use_merkle_quota(deps.storage, &info.sender, proof.quota)?;
+        }
+        (None, None) => {
+            if allowlist_only && !is_owner {
+                return Err(ContractError::AllowlistOnly {});
+            }
+        }
+        (Some(_), Some(_)) => {
+            return Err(ContractError::Std(StdError::generic_err(
+                "expected either an allowlist signature or a merkle proof, not both",
+            )))
+        }
+    }
+
+    // the contract owner can mint for free
+    if !is_owner {
+        check_sufficient_funds(info.funds, price)?;
+    }
+    check_wallet_limit(deps.storage, info.sender.clone(), config.wallet_limit)?;
+    if let Some((_, phase)) = phase {
+        use_phase_allocation(deps.storage, phase, &info.sender)?;
+    }
+
+    let numeric_id = mint_token(deps.storage, &env, &config, &info.sender)?;
+
     Ok(Response::new()
         .add_attribute("action", "mint")
-        .add_attribute("minter", info.sender))
+        .add_attribute("minter", info.sender)
+        .add_attribute("token_id", numeric_id))
+}
+
+pub fn execute_batch_mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipients: Vec<(String, u32)>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if pause_flags(deps.storage)?.minting {
+        return Err(ContractError::MintingPaused {});
+    }
+
+    let requested: u64 = recipients.iter().map(|(_, count)| *count as u64).sum();
+    let remaining = config
+        .token_supply
+        .saturating_sub(minted_count(deps.storage)?);
+    if requested > remaining {
+        return Err(ContractError::BatchExceedsSupply {
+            requested,
+            remaining,
+        });
+    }
+
+    let mut response = Response::new()
+        .add_attribute("action", "batch_mint")
+        .add_attribute("minter", info.sender)
+        .add_attribute("count", requested.to_string());
+    for (recipient, count) in recipients {
+        let recipient = deps.api.addr_validate(&recipient)?;
+        for _ in 0..count {
+            let numeric_id = mint_token(deps.storage, &env, &config, &recipient)?;
+            response = response.add_event(
+                Event::new("mint")
+                    .add_attribute("owner", recipient.to_string())
+                    .add_attribute("token_id", numeric_id),
+            );
+        }
+    }
+    Ok(response)
+}
+
+/// Creates the next sequential token for `owner`, returning its numeric id.
+fn mint_token(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    owner: &Addr,
+) -> Result<String, ContractError> {
+    check_no_pending_migration(storage)?;
+    let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
+    let minted = minted_count(storage)? + 1;
+    let numeric_id = minted.to_string();
+    let token_id = full_token_id(&token_prefix(storage)?, numeric_id.clone())?;
+    let token = InternTokenInfo {
+        owner: owner.clone(),
+        approvals: vec![],
+        name: token_id.clone(),
+        description: "".to_string(),
+        image: None,
+        extension: roll_traits(&config.starting_traits, &mint_seed(env, owner, &token_id)),
+    };
+    tokens().update(storage, &token_id, |old| match old {
+        Some(_) => Err(ContractError::Claimed {}),
+        None => Ok(token),
+    })?;
+    cw721_contract.increment_tokens(storage)?;
+    increment_owner_tokens(storage, owner)?;
+    MINTED.save(storage, &minted)?;
+    // imports could otherwise add tokens next to the minted ones
+    if !import_sealed(storage)? {
+        IMPORT_SEALED.save(storage, &true)?;
+    }
+    Ok(numeric_id)
+}
+
+pub fn execute_burn(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+) -> Result<Response, ContractError> {
+    if pause_flags(deps.storage)?.transfers {
+        return Err(ContractError::TransfersPaused {});
+    }
+    check_no_pending_migration(deps.storage)?;
+    let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
+    let config = CONFIG.load(deps.storage)?;
+    let full_id = token_id.full(&token_prefix(deps.storage)?);
+    let token = cw721_contract.tokens.load(deps.storage, &full_id)?;
+
+    // staked interns are held by the staking contract until they are withdrawn
+    if token.owner == config.staking_contract {
+        return Err(ContractError::TokenStaked {});
+    }
+    cw721_contract.check_can_approve(deps.as_ref(), &env, &info, &token)?;
+
+    tokens().remove(deps.storage, &full_id)?;
+    decrement_owner_tokens(deps.storage, &token.owner)?;
+    let num_tokens = cw721_contract.token_count(deps.storage)?;
+    cw721_contract
+        .token_count
+        .save(deps.storage, &num_tokens.saturating_sub(1))?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "burn")
+        .add_attribute("sender", info.sender)
+        .add_attribute("token_id", token_id.to_string());
+    if let Some(burn_hook) = config.burn_hook {
+        let hook = InternBurnedMsg {
+            token_id,
+            owner: token.owner.to_string(),
+            experience: token.extension.experience,
+            gold: token.extension.gold,
+        };
+        response = response.add_message(hook.into_cosmos_msg(burn_hook)?);
+    }
+    Ok(response)
 }
 
 pub fn execute_update_traits(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    token_id: String,
+    token_id: TokenId,
     exp: u64,
     gold: u64,
     stamina: u64,
 ) -> Result<Response, ContractError> {
+    if pause_flags(deps.storage)?.trait_updates {
+        return Err(ContractError::TraitUpdatesPaused {});
+    }
     let config = CONFIG.load(deps.storage)?;
-    let staking_contract = config.staking_contract;
-    let token = tokens().load(deps.storage, &token_id)?;
+    let full_id = token_id.full(&token_prefix(deps.storage)?);
+    let token = tokens().load(deps.storage, &full_id)?;
 
-    //right now, only the staking contract can update the traits
-    if info.sender != staking_contract {
-        return Err(ContractError::Unauthorized {});
-    }
+    let changed: Vec<TraitKind> = [
+        (TraitKind::Experience, token.extension.experience != exp),
+        (TraitKind::Gold, token.extension.gold != gold),
+        (TraitKind::Stamina, token.extension.stamina != stamina),
+    ]
+    .iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(kind, _)| *kind)
+    .collect();
+    check_trait_permission(deps.storage, &config, &info.sender, &changed)?;
 
     let mut new_token = token.clone();
     new_token.extension.experience = exp;
     new_token.extension.gold = gold;
     new_token.extension.stamina = stamina;
-    tokens().replace(deps.storage, &token_id, Some(&new_token), Some(&token))?;
+    tokens().replace(deps.storage, &full_id, Some(&new_token), Some(&token))?;
 
     Ok(Response::new()
         .add_attribute("action", "update_traits")
+        .add_attribute("token_id", token_id.to_string())
         .add_attribute("experience", exp.to_string())
         .add_attribute("gold", gold.to_string())
         .add_attribute("stamina", stamina.to_string()))
 }
 
-#[allow(dead_code)]
+/// Applies signed deltas to a token's traits. Unlike `UpdateTrait`, concurrent adjustments
+/// compose instead of overwriting each other.
+pub fn execute_adjust_traits(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    experience: i64,
+    gold: i64,
+    stamina: i64,
+) -> Result<Response, ContractError> {
+    if pause_flags(deps.storage)?.trait_updates {
+        return Err(ContractError::TraitUpdatesPaused {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let changed: Vec<TraitKind> = [
+        (TraitKind::Experience, experience),
+        (TraitKind::Gold, gold),
+        (TraitKind::Stamina, stamina),
+    ]
+    .iter()
+    .filter(|(_, delta)| *delta != 0)
+    .map(|(kind, _)| *kind)
+    .collect();
+    check_trait_permission(deps.storage, &config, &info.sender, &changed)?;
+
+    let full_id = token_id.full(&token_prefix(deps.storage)?);
+    let token = tokens().load(deps.storage, &full_id)?;
+    let mut new_token = token.clone();
+    new_token.extension.experience = apply_delta(
+        token.extension.experience,
+        experience,
+        TraitKind::Experience,
+    )?;
+    new_token.extension.gold = apply_delta(token.extension.gold, gold, TraitKind::Gold)?;
+    new_token.extension.stamina =
+        apply_delta(token.extension.stamina, stamina, TraitKind::Stamina)?;
+    tokens().replace(deps.storage, &full_id, Some(&new_token), Some(&token))?;
+
+    let traits = new_token.extension;
+    Ok(Response::new()
+        .set_data(to_binary(&traits)?)
+        .add_attribute("action", "adjust_traits")
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("experience", traits.experience.to_string())
+        .add_attribute("gold", traits.gold.to_string())
+        .add_attribute("stamina", traits.stamina.to_string()))
+}
+
+fn apply_delta(value: u64, delta: i64, kind: TraitKind) -> Result<u64, ContractError> {
+    let adjusted = if delta >= 0 {
+        value.checked_add(delta as u64)
+    } else {
+        value.checked_sub(delta.unsigned_abs())
+    };
+    adjusted.ok_or_else(|| ContractError::TraitOutOfRange {
+        name: kind.as_str().to_string(),
+    })
+}
+
+/// The staking contract may change every trait, registered updaters only the ones they
+/// were granted
+fn check_trait_permission(
+    storage: &dyn Storage,
+    config: &Config,
+    sender: &Addr,
+    changed: &[TraitKind],
+) -> Result<(), ContractError> {
+    if sender == &config.staking_contract {
+        return Ok(());
+    }
+    let granted = TRAIT_UPDATERS
+        .may_load(storage, sender)?
+        .ok_or(ContractError::Unauthorized {})?;
+    match changed.iter().find(|kind| !granted.contains(kind)) {
+        Some(kind) => Err(ContractError::TraitNotPermitted {
+            name: kind.as_str().to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn execute_grant_trait_updater(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    mut traits: Vec<TraitKind>,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if traits.is_empty() {
+        return Err(ContractError::NoTraitsGranted {});
+    }
+    traits.sort();
+    traits.dedup();
+
+    let updater = deps.api.addr_validate(&address)?;
+    TRAIT_UPDATERS.save(deps.storage, &updater, &traits)?;
+
+    let names: Vec<&str> = traits.iter().map(TraitKind::as_str).collect();
+    Ok(Response::new()
+        .add_attribute("action", "grant_trait_updater")
+        .add_attribute("updater", updater)
+        .add_attribute("traits", names.join(",")))
+}
+
+pub fn execute_revoke_trait_updater(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let updater = deps.api.addr_validate(&address)?;
+    TRAIT_UPDATERS.remove(deps.storage, &updater);
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_trait_updater")
+        .add_attribute("updater", updater))
+}
+
+pub fn execute_add_wallet_limit_exemption(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    WALLET_LIMIT_EXEMPTIONS.save(deps.storage, &address, &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_wallet_limit_exemption")
+        .add_attribute("address", address))
+}
+
+pub fn execute_remove_wallet_limit_exemption(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    WALLET_LIMIT_EXEMPTIONS.remove(deps.storage, &address);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_wallet_limit_exemption")
+        .add_attribute("address", address))
+}
+
+pub fn execute_import_tokens(
+    deps: DepsMut,
+    info: MessageInfo,
+    imported: Vec<ExportedToken>,
+    minted: Option<u64>,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if import_sealed(deps.storage)? {
+        return Err(ContractError::ImportSealed {});
+    }
+    check_no_pending_migration(deps.storage)?;
+
+    let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
+    let prefix = token_prefix(deps.storage)?;
+    let mut new_minted = minted_count(deps.storage)?.max(minted.unwrap_or(0));
+    let count = imported.len();
+    for ExportedToken {
+        token_id,
+        mut token,
+    } in imported
+    {
+        // queries read token ids back from the names
+        let full_id = token_id.full(&prefix);
+        if token.name != full_id {
+            return Err(StdError::generic_err(format!(
+                "token {} must be named '{}'",
+                token_id, full_id
+            ))
+            .into());
+        }
+        token.owner = deps.api.addr_validate(token.owner.as_str())?;
+        for approval in token.approvals.iter_mut() {
+            approval.spender = deps.api.addr_validate(approval.spender.as_str())?;
+        }
+        let owner = token.owner.clone();
+        tokens().update(deps.storage, &full_id, |old| match old {
+            Some(_) => Err(ContractError::Claimed {}),
+            None => Ok(token),
+        })?;
+        cw721_contract.increment_tokens(deps.storage)?;
+        increment_owner_tokens(deps.storage, &owner)?;
+        new_minted = new_minted.max(token_id.u64());
+    }
+    let token_supply = CONFIG.load(deps.storage)?.token_supply;
+    if new_minted > token_supply || cw721_contract.token_count(deps.storage)? > token_supply {
+        return Err(ContractError::ImportExceedsSupply { token_supply });
+    }
+    MINTED.save(deps.storage, &new_minted)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "import_tokens")
+        .add_attribute("count", count.to_string())
+        .add_attribute("minted", new_minted.to_string()))
+}
+
+pub fn execute_resume_migration(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let limit = migration_batch_size(limit);
+    let (processed, done) = continue_index_backfill(deps.storage, limit)?;
+    Ok(Response::new()
+        .add_attribute("action", "resume_migration")
+        .add_attribute("processed", processed.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+// the owner counters and indexes are rebuilt token by token while a backfill is pending, so
+// nothing may move, create or remove tokens in between
+fn check_no_pending_migration(storage: &dyn Storage) -> Result<(), ContractError> {
+    if INDEX_BACKFILL.may_load(storage)?.is_some() {
+        return Err(ContractError::MigrationPending {});
+    }
+    Ok(())
+}
+
+pub fn execute_seal_import(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    IMPORT_SEALED.save(deps.storage, &true)?;
+    Ok(Response::new().add_attribute("action", "seal_import"))
+}
+
 fn check_sufficient_funds(funds: Vec<Coin>, required: Coin) -> Result<(), ContractError> {
     if required.amount.u128() == 0 {
         return Ok(());
@@ -92,26 +601,35 @@ fn check_sufficient_funds(funds: Vec<Coin>, required: Coin) -> Result<(), Contra
     }
 }
 
-#[allow(dead_code)]
 fn check_wallet_limit(
     storage: &dyn Storage,
     owner: cosmwasm_std::Addr,
     limit: u32,
 ) -> Result<(), ContractError> {
-    let num_wallet_tokens = tokens()
-        .idx
-        .owner
-        .prefix(owner)
-        .range(storage, None, None, Order::Ascending)
-        .count();
-
-    if num_wallet_tokens >= limit as usize {
+    if owner_token_count(storage, &owner)? >= limit as u64 {
         Err(ContractError::WalletLimit {})
     } else {
         Ok(())
     }
 }
 
+// the staking contract and exempted addresses, e.g. marketplaces, can hold any number of tokens.
+// Tokens they hand out aren't limited either, so stakers can always withdraw.
+fn check_transfer_wallet_limit(
+    storage: &dyn Storage,
+    previous_owner: &Addr,
+    recipient: &Addr,
+) -> Result<(), ContractError> {
+    let config = CONFIG.load(storage)?;
+    let unlimited = |address: &Addr| {
+        *address == config.staking_contract || WALLET_LIMIT_EXEMPTIONS.has(storage, address)
+    };
+    if unlimited(previous_owner) || unlimited(recipient) {
+        return Ok(());
+    }
+    check_wallet_limit(storage, recipient.clone(), config.wallet_limit)
+}
+
 pub fn execute_update_config(
     deps: DepsMut,
     info: MessageInfo,
@@ -121,10 +639,45 @@ pub fn execute_update_config(
     if info.sender != owner {
         return Err(ContractError::Unauthorized {});
     }
+    validate_config(deps.api, &config)?;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new().add_attribute("action", "update_config"))
 }
 
+pub fn execute_update_metadata_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    metadata: MetadataConfig,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    metadata.validate()?;
+    METADATA_CONFIG.save(deps.storage, &metadata)?;
+    Ok(Response::new().add_attribute("action", "update_metadata_config"))
+}
+
+pub fn execute_update_pause_flags(
+    deps: DepsMut,
+    info: MessageInfo,
+    flags: PauseFlags,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    PAUSE_FLAGS.save(deps.storage, &flags)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_pause_flags")
+        .add_event(
+            Event::new("pause_status")
+                .add_attribute("minting", flags.minting.to_string())
+                .add_attribute("transfers", flags.transfers.to_string())
+                .add_attribute("trait_updates", flags.trait_updates.to_string()),
+        ))
+}
+
 pub fn execute_withdraw(
     deps: DepsMut,
     _env: Env,
@@ -142,14 +695,92 @@ pub fn execute_withdraw(
     }))
 }
 
-pub fn cw721_base_execute(
+pub fn execute_propose_new_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_owner: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::OwnershipProposalExpired {});
+    }
+    let proposal = OwnershipProposal {
+        owner: deps.api.addr_validate(&new_owner)?,
+        expires,
+    };
+    OWNERSHIP_PROPOSAL.save(deps.storage, &proposal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_new_owner")
+        .add_attribute("owner", owner)
+        .add_attribute("proposed_owner", proposal.owner))
+}
+
+pub fn execute_accept_ownership(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let proposal = OWNERSHIP_PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoOwnershipProposal {})?;
+    if info.sender != proposal.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if proposal.expires.is_expired(&env.block) {
+        return Err(ContractError::OwnershipProposalExpired {});
+    }
+
+    let previous_owner = OWNER.load(deps.storage)?;
+    OWNER.save(deps.storage, &proposal.owner.to_string())?;
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership")
+        .add_attribute("previous_owner", previous_owner)
+        .add_attribute("owner", proposal.owner))
+}
+
+pub fn execute_cancel_ownership_proposal(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if OWNERSHIP_PROPOSAL.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoOwnershipProposal {});
+    }
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel_ownership_proposal"))
+}
+
+pub fn cw721_base_execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
-    let cw721_msg: Cw721ExecuteMsg<InternExtension> = msg.into();
+    let prefix = token_prefix(deps.storage)?;
+    let cw721_msg = Cw721ExecuteMsg::<InternExtension>::try_from(msg)?;
+    // revoking only takes rights away, so holders can still do it while transfers are paused
+    let is_revoke = matches!(
+        cw721_msg,
+        Cw721ExecuteMsg::Revoke { .. } | Cw721ExecuteMsg::RevokeAll { .. }
+    );
+    if !is_revoke && pause_flags(deps.storage)?.transfers {
+        return Err(ContractError::TransfersPaused {});
+    }
     let cw721_msg_full_token_id = match cw721_msg {
         Cw721ExecuteMsg::Approve {
             spender,
@@ -158,18 +789,18 @@ pub fn cw721_base_execute(
         } => Cw721ExecuteMsg::Approve {
             spender,
             expires,
-            token_id: full_token_id(token_id)?,
+            token_id: full_token_id(&prefix, token_id)?,
         },
         Cw721ExecuteMsg::Revoke { spender, token_id } => Cw721ExecuteMsg::Revoke {
             spender,
-            token_id: full_token_id(token_id)?,
+            token_id: full_token_id(&prefix, token_id)?,
         },
         Cw721ExecuteMsg::TransferNft {
             recipient,
             token_id,
         } => Cw721ExecuteMsg::TransferNft {
             recipient,
-            token_id: full_token_id(token_id)?,
+            token_id: full_token_id(&prefix, token_id)?,
         },
         Cw721ExecuteMsg::SendNft {
             contract,
@@ -178,22 +809,56 @@ pub fn cw721_base_execute(
         } => Cw721ExecuteMsg::SendNft {
             contract,
             msg,
-            token_id: full_token_id(token_id)?,
+            token_id: full_token_id(&prefix, token_id)?,
         },
         _ => cw721_msg,
     };
 
+    // cw721-base moves the token itself, so the counters follow its owner once it succeeds
+    let moved_token = match &cw721_msg_full_token_id {
+        Cw721ExecuteMsg::TransferNft {
+            recipient,
+            token_id,
+        }
+        | Cw721ExecuteMsg::SendNft {
+            contract: recipient,
+            token_id,
+            ..
+        } => {
+            check_no_pending_migration(deps.storage)?;
+            let previous_owner = tokens().load(deps.storage, token_id)?.owner;
+            let recipient = deps.api.addr_validate(recipient)?;
+            if recipient != previous_owner {
+                check_transfer_wallet_limit(deps.storage, &previous_owner, &recipient)?;
+            }
+            Some((token_id.clone(), previous_owner))
+        }
+        _ => None,
+    };
+
     let mut response = (match cw721_msg_full_token_id {
         Cw721ExecuteMsg::SendNft {
             contract,
             token_id,
             msg,
-        } => execute_send_nft(deps, env, info, contract, token_id, msg),
+        } => execute_send_nft(deps.branch(), env, info, contract, token_id, msg),
         _ => cw721_contract
-            .execute(deps, env, info, cw721_msg_full_token_id)
+            .execute(deps.branch(), env, info, cw721_msg_full_token_id)
             .map_err(|err| err.into()),
     })?;
 
+    if let Some((token_id, previous_owner)) = moved_token {
+        let token = tokens().load(deps.storage, &token_id)?;
+        // cw721-base only updates its owner index, so the others are moved here
+        let previous = InternTokenInfo {
+            owner: previous_owner.clone(),
+            ..token.clone()
+        };
+        tokens().replace(deps.storage, &token_id, Some(&token), Some(&previous))?;
+        decrement_owner_tokens(deps.storage, &previous_owner)?;
+        increment_owner_tokens(deps.storage, &token.owner)?;
+    }
+
     response.attributes = response
         .attributes
         .iter()
@@ -201,7 +866,7 @@ pub fn cw721_base_execute(
             if attr.key == "token_id" {
                 Attribute::new(
                     "token_id",
-                    numeric_token_id(attr.value.to_string()).unwrap(),
+                    numeric_token_id(&prefix, attr.value.to_string()).unwrap(),
                 )
             } else {
                 attr.clone()
@@ -220,12 +885,13 @@ pub fn execute_send_nft(
     msg: Binary,
 ) -> Result<Response, ContractError> {
     let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
+    let prefix = token_prefix(deps.storage)?;
     // Transfer token
     cw721_contract._transfer_nft(deps, &env, &info, &contract, &token_id)?;
 
     let send = Cw721ReceiveMsg {
         sender: info.sender.to_string(),
-        token_id: numeric_token_id(token_id.clone())?,
+        token_id: numeric_token_id(&prefix, token_id.clone())?,
         msg,
     };
 
@@ -238,8 +904,32 @@ pub fn execute_send_nft(
         .add_attribute("token_id", token_id))
 }
 
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default().add_attribute("action", "migrate"))
+pub fn migrate(
+    deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg,
+    from_version: &Version,
+) -> StdResult<Response> {
+    let applied = run_migrations(deps.storage, MIGRATIONS, from_version)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(starting_traits) = msg.starting_traits {
+        config.starting_traits = starting_traits;
+    }
+    if let Some(burn_hook) = msg.burn_hook {
+        config.burn_hook = Some(burn_hook);
+    }
+    validate_config(deps.api, &config)?;
+    CONFIG.save(deps.storage, &config)?;
+    if let Some(metadata) = msg.metadata {
+        metadata.validate()?;
+        METADATA_CONFIG.save(deps.storage, &metadata)?;
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from_version.to_string())
+        .add_attribute("migrations", applied.join(",")))
 }
 
 #[cfg(test)]
@@ -247,10 +937,9 @@ mod test {
     use super::*;
 
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{to_binary, Addr};
-    use cw721::{Cw721ReceiveMsg, Expiration};
+    use cosmwasm_std::{from_slice, to_binary, Addr};
+    use cw721::Cw721ReceiveMsg;
     use cw721_base::state::Approval;
-    use internnft::nft::InternTokenInfo;
 
     const ADDR1: &str = "addr1";
     const ADDR2: &str = "addr2";
@@ -285,13 +974,32 @@ mod test {
     }
 
     fn setup_storage(deps: DepsMut) {
+        let config = Config {
+            mint_fee: Coin::new(0, "uluna"),
+            token_supply: 10000,
+            wallet_limit: 5,
+            staking_contract: "staking_contract".to_string(),
+            starting_traits: Default::default(),
+            allowlist_public_key: None,
+            allowlist_merkle_root: None,
+            presale: false,
+            mint_phases: vec![],
+            burn_hook: None,
+        };
+        CONFIG.save(deps.storage, &config).unwrap();
         for token in token_examples().iter() {
             tokens().save(deps.storage, &token.name, token).unwrap();
         }
     }
 
-    fn numeric_id_error() -> ContractError {
-        ContractError::Std(StdError::generic_err("expected numeric token identifier"))
+    fn assert_rejects_full_id(json: &[u8]) {
+        let err = from_slice::<ExecuteMsg>(json).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected numeric token identifier"),
+            "{}",
+            err
+        );
     }
 
     #[test]
@@ -300,17 +1008,7 @@ mod test {
         setup_storage(deps.as_mut());
 
         // blocks full token identifiers
-        let err = cw721_base_execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info(ADDR1, &[]),
-            ExecuteMsg::TransferNft {
-                recipient: ADDR2.to_string(),
-                token_id: "intern #1".to_string(),
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"transfer_nft":{"recipient":"addr2","token_id":"intern #1"}}"#);
 
         // transfer intern #1
         let res = cw721_base_execute(
@@ -319,7 +1017,7 @@ mod test {
             mock_info(ADDR1, &[]),
             ExecuteMsg::TransferNft {
                 recipient: ADDR2.to_string(),
-                token_id: "1".to_string(),
+                token_id: TokenId::new(1),
             },
         )
         .unwrap();
@@ -342,18 +1040,7 @@ mod test {
         setup_storage(deps.as_mut());
 
         // approve blocks full token identifiers
-        let err = cw721_base_execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info(ADDR1, &[]),
-            ExecuteMsg::Approve {
-                spender: ADDR2.to_string(),
-                token_id: "intern #1".to_string(),
-                expires: None,
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"approve":{"spender":"addr2","token_id":"intern #1"}}"#);
 
         // grant an approval
         let res = cw721_base_execute(
@@ -362,7 +1049,7 @@ mod test {
             mock_info(ADDR1, &[]),
             ExecuteMsg::Approve {
                 spender: ADDR2.to_string(),
-                token_id: "1".to_string(),
+                token_id: TokenId::new(1),
                 expires: None,
             },
         )
@@ -386,17 +1073,7 @@ mod test {
         );
 
         // revoke blocks full token identifiers
-        let err = cw721_base_execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info(ADDR1, &[]),
-            ExecuteMsg::Revoke {
-                spender: ADDR2.to_string(),
-                token_id: "intern #1".to_string(),
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"revoke":{"spender":"addr2","token_id":"intern #1"}}"#);
 
         // revoke the approval
         let res = cw721_base_execute(
@@ -405,7 +1082,7 @@ mod test {
             mock_info(ADDR1, &[]),
             ExecuteMsg::Revoke {
                 spender: ADDR2.to_string(),
-                token_id: "1".to_string(),
+                token_id: TokenId::new(1),
             },
         )
         .unwrap();
@@ -432,18 +1109,9 @@ mod test {
         let msg = to_binary("my msg").unwrap();
 
         // blocks full token identifiers
-        let err = cw721_base_execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info(ADDR1, &[]),
-            ExecuteMsg::SendNft {
-                contract: target.clone(),
-                token_id: "intern #1".to_string(),
-                msg: msg.clone(),
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(
+            br#"{"send_nft":{"contract":"target","token_id":"intern #1","msg":""}}"#,
+        );
 
         // send a token to a contract
         let res = cw721_base_execute(
@@ -452,7 +1120,7 @@ mod test {
             mock_info(ADDR1, &[]),
             ExecuteMsg::SendNft {
                 contract: target.clone(),
-                token_id: token_id.clone(),
+                token_id: TokenId::new(1),
                 msg: msg.clone(),
             },
         )
@@ -474,4 +1142,27 @@ mod test {
                 .add_attribute("token_id", token_id)
         );
     }
+
+    #[test]
+    fn cw721_unsupported_message() {
+        let mut deps = mock_dependencies(&[]);
+        setup_storage(deps.as_mut());
+
+        // messages without a cw721 equivalent are rejected instead of trapping
+        let err = cw721_base_execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADDR1, &[]),
+            ExecuteMsg::Burn {
+                token_id: TokenId::new(1),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UnsupportedMessage {
+                msg: "Burn { token_id: TokenId(1) }".to_string()
+            }
+        );
+    }
 }
@@ -1,7 +1,11 @@
+pub mod allowlist;
 pub mod contract;
 pub mod error;
 pub mod execute;
+pub mod migrations;
+pub mod phases;
 pub mod query;
 pub mod state;
+pub mod traits;
 
 mod contract_tests;
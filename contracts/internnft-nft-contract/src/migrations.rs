@@ -0,0 +1,284 @@
+use cosmwasm_std::{Addr, Order, StdError, StdResult, Storage};
+use cw_storage_plus::Bound;
+use internnft::migrations::Migration;
+use internnft::nft::{MetadataConfig, DEFAULT_TOKEN_PREFIX};
+
+use crate::state::{
+    increment_owner_tokens, minted_count, tokens, CONFIG, IMPORT_SEALED, INDEX_BACKFILL,
+    METADATA_CONFIG, OWNER_TOKEN_COUNTS, TOKEN_PREFIX,
+};
+
+pub const MIGRATIONS: &[Migration] = &[
+    ("0.3.0", "upgrade_config", upgrade_config),
+    ("0.3.0", "backfill_token_indexes", backfill_token_indexes),
+    ("0.3.0", "seal_import", seal_import),
+];
+
+/// Stores the settings in the current layout. Config fields added since 0.2 load with their
+/// defaults, and contracts instantiated before the token prefix and metadata settings have none.
+fn upgrade_config(storage: &mut dyn Storage) -> StdResult<()> {
+    let config = CONFIG.load(storage)?;
+    CONFIG.save(storage, &config)?;
+    if TOKEN_PREFIX.may_load(storage)?.is_none() {
+        TOKEN_PREFIX.save(storage, &DEFAULT_TOKEN_PREFIX.to_string())?;
+    }
+    if METADATA_CONFIG.may_load(storage)?.is_none() {
+        METADATA_CONFIG.save(storage, &MetadataConfig::default())?;
+    }
+    Ok(())
+}
+
+/// Starts adding tokens minted before the indexes existed to them, see
+/// `continue_index_backfill`. The owner counters are rebuilt along the way, so leftovers are
+/// cleared here. There are none on deployments from before 0.3.0.
+fn backfill_token_indexes(storage: &mut dyn Storage) -> StdResult<()> {
+    let stale_owners: Vec<Vec<u8>> = OWNER_TOKEN_COUNTS
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+    for owner in stale_owners {
+        let owner = Addr::unchecked(String::from_utf8(owner).map_err(StdError::invalid_utf8)?);
+        OWNER_TOKEN_COUNTS.remove(storage, &owner);
+    }
+    INDEX_BACKFILL.save(storage, &String::new())
+}
+
+/// `ImportTokens` is only meant for seeding new deployments, so it is closed on ones that
+/// already minted tokens before it existed.
+fn seal_import(storage: &mut dyn Storage) -> StdResult<()> {
+    if IMPORT_SEALED.may_load(storage)?.is_none() && minted_count(storage)? > 0 {
+        IMPORT_SEALED.save(storage, &true)?;
+    }
+    Ok(())
+}
+
+/// Indexes and counts the next `limit` tokens of a pending backfill, as it can't fit into one
+/// transaction for a full collection. Returns how many were processed and whether it's done.
+pub fn continue_index_backfill(
+    storage: &mut dyn Storage,
+    limit: usize,
+) -> StdResult<(usize, bool)> {
+    let mut cursor = match INDEX_BACKFILL.may_load(storage)? {
+        Some(cursor) => cursor,
+        None => return Ok((0, true)),
+    };
+    let batch: StdResult<Vec<_>> = tokens()
+        .range(
+            storage,
+            Some(Bound::exclusive(cursor.clone())),
+            None,
+            Order::Ascending,
+        )
+        .take(limit + 1)
+        .collect();
+    let mut batch = batch?;
+    let done = batch.len() <= limit;
+    batch.truncate(limit);
+
+    let processed = batch.len();
+    for (key, token) in batch {
+        // saving a token over itself rebuilds all of its index entries
+        cursor = String::from_utf8(key).map_err(StdError::invalid_utf8)?;
+        tokens().replace(storage, &cursor, Some(&token), Some(&token))?;
+        increment_owner_tokens(storage, &token.owner)?;
+    }
+    if done {
+        INDEX_BACKFILL.remove(storage);
+    } else {
+        INDEX_BACKFILL.save(storage, &cursor)?;
+    }
+    Ok((processed, done))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::Coin;
+    use cw_storage_plus::{Item, Map};
+    use internnft::migrations::run_migrations;
+    use internnft::nft::{Config, InternExtension, InternTokenInfo, TraitRanges};
+    use semver::Version;
+    use serde::{Deserialize, Serialize};
+
+    use crate::state::owner_token_count;
+
+    #[derive(Serialize, Deserialize)]
+    struct ConfigV0_2 {
+        token_supply: u64,
+        wallet_limit: u32,
+        mint_fee: Coin,
+        staking_contract: String,
+    }
+
+    fn intern(owner: &str, experience: u64, gold: u64) -> InternTokenInfo {
+        InternTokenInfo {
+            owner: Addr::unchecked(owner),
+            approvals: vec![],
+            name: "".to_string(),
+            description: "".to_string(),
+            image: None,
+            extension: InternExtension {
+                experience,
+                gold,
+                stamina: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn migrations_are_ordered() {
+        let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        let versions: Vec<Version> = MIGRATIONS
+            .iter()
+            .map(|(version, _, _)| Version::parse(version).unwrap())
+            .collect();
+        assert!(versions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(versions.iter().all(|version| *version <= current));
+    }
+
+    #[test]
+    fn only_newer_steps_run() {
+        let mut deps = mock_dependencies(&[]);
+        let v0_2 = ConfigV0_2 {
+            token_supply: 100,
+            wallet_limit: 5,
+            mint_fee: Coin::new(0, "uluna"),
+            staking_contract: "staking_contract".to_string(),
+        };
+        Item::new("config").save(&mut deps.storage, &v0_2).unwrap();
+
+        let applied =
+            run_migrations(&mut deps.storage, MIGRATIONS, &Version::new(0, 2, 2)).unwrap();
+        assert_eq!(
+            applied,
+            vec!["upgrade_config", "backfill_token_indexes", "seal_import"]
+        );
+
+        let applied =
+            run_migrations(&mut deps.storage, MIGRATIONS, &Version::new(0, 3, 0)).unwrap();
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn upgrade_config_fills_new_settings() {
+        let mut deps = mock_dependencies(&[]);
+        let v0_2 = ConfigV0_2 {
+            token_supply: 100,
+            wallet_limit: 5,
+            mint_fee: Coin::new(1_000_000, "uluna"),
+            staking_contract: "staking_contract".to_string(),
+        };
+        Item::new("config").save(&mut deps.storage, &v0_2).unwrap();
+
+        upgrade_config(&mut deps.storage).unwrap();
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                token_supply: 100,
+                wallet_limit: 5,
+                mint_fee: Coin::new(1_000_000, "uluna"),
+                staking_contract: "staking_contract".to_string(),
+                starting_traits: TraitRanges::default(),
+                allowlist_public_key: None,
+                allowlist_merkle_root: None,
+                presale: false,
+                mint_phases: vec![],
+                burn_hook: None,
+            }
+        );
+        assert_eq!(
+            TOKEN_PREFIX.load(&deps.storage).unwrap(),
+            DEFAULT_TOKEN_PREFIX
+        );
+        assert_eq!(
+            METADATA_CONFIG.load(&deps.storage).unwrap(),
+            MetadataConfig::default()
+        );
+
+        // settings that are already stored are kept
+        TOKEN_PREFIX
+            .save(&mut deps.storage, &"xyz #".to_string())
+            .unwrap();
+        upgrade_config(&mut deps.storage).unwrap();
+        assert_eq!(TOKEN_PREFIX.load(&deps.storage).unwrap(), "xyz #");
+    }
+
+    #[test]
+    fn backfill_token_indexes_rebuilds_indexes_and_counters() {
+        let mut deps = mock_dependencies(&[]);
+        // tokens saved by a version without the indexes only have their primary entries
+        let legacy_tokens = Map::<&str, InternTokenInfo>::new("tokens");
+        for (key, token) in [
+            ("intern #1", intern("addr1", 10, 300)),
+            ("intern #2", intern("addr1", 30, 100)),
+            ("intern #3", intern("addr2", 20, 200)),
+        ]
+        .iter()
+        {
+            legacy_tokens.save(&mut deps.storage, key, token).unwrap();
+        }
+        OWNER_TOKEN_COUNTS
+            .save(&mut deps.storage, &Addr::unchecked("addr3"), &4)
+            .unwrap();
+
+        backfill_token_indexes(&mut deps.storage).unwrap();
+        assert_eq!(
+            owner_token_count(&deps.storage, &Addr::unchecked("addr3")).unwrap(),
+            0
+        );
+
+        // the tokens are processed in batches
+        assert_eq!(
+            continue_index_backfill(&mut deps.storage, 2).unwrap(),
+            (2, false)
+        );
+        let by_gold = tokens()
+            .idx
+            .gold
+            .keys(&deps.storage, None, None, Order::Descending)
+            .count();
+        assert_eq!(by_gold, 2);
+        assert_eq!(
+            continue_index_backfill(&mut deps.storage, 2).unwrap(),
+            (1, true)
+        );
+        assert!(INDEX_BACKFILL.may_load(&deps.storage).unwrap().is_none());
+        assert_eq!(
+            continue_index_backfill(&mut deps.storage, 2).unwrap(),
+            (0, true)
+        );
+
+        let by_experience: Vec<Vec<u8>> = tokens()
+            .idx
+            .experience
+            .keys(&deps.storage, None, None, Order::Descending)
+            .collect();
+        assert_eq!(by_experience.len(), 3);
+        let by_gold = tokens()
+            .idx
+            .gold
+            .keys(&deps.storage, None, None, Order::Descending)
+            .count();
+        assert_eq!(by_gold, 3);
+        let by_number = tokens()
+            .idx
+            .number
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .count();
+        assert_eq!(by_number, 3);
+        assert_eq!(
+            owner_token_count(&deps.storage, &Addr::unchecked("addr1")).unwrap(),
+            2
+        );
+        assert_eq!(
+            owner_token_count(&deps.storage, &Addr::unchecked("addr2")).unwrap(),
+            1
+        );
+        assert_eq!(
+            owner_token_count(&deps.storage, &Addr::unchecked("addr3")).unwrap(),
+            0
+        );
+    }
+}
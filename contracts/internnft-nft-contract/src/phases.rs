@@ -0,0 +1,47 @@
+use cosmwasm_std::{Addr, StdResult, Storage};
+use cw_storage_plus::U64Key;
+use internnft::nft::MintPhase;
+
+use crate::error::ContractError;
+use crate::state::{PHASE_MINTED, PHASE_WALLET_MINTED};
+
+// phases don't overlap, so their start identifies them even when the schedule is edited
+fn phase_key(phase: &MintPhase) -> U64Key {
+    U64Key::new(phase.start.nanos())
+}
+
+pub fn phase_minted(storage: &dyn Storage, phase: &MintPhase) -> StdResult<u64> {
+    Ok(PHASE_MINTED
+        .may_load(storage, phase_key(phase))?
+        .unwrap_or(0))
+}
+
+pub fn phase_wallet_minted(
+    storage: &dyn Storage,
+    phase: &MintPhase,
+    wallet: &Addr,
+) -> StdResult<u32> {
+    Ok(PHASE_WALLET_MINTED
+        .may_load(storage, (phase_key(phase), wallet))?
+        .unwrap_or(0))
+}
+
+/// Records one more mint by `sender` during the given phase, enforcing its caps.
+pub fn use_phase_allocation(
+    storage: &mut dyn Storage,
+    phase: &MintPhase,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    let minted = phase_minted(storage, phase)?;
+    if minted >= phase.supply_cap {
+        return Err(ContractError::PhaseSupplyExhausted {});
+    }
+    let wallet_minted = phase_wallet_minted(storage, phase, sender)?;
+    if wallet_minted >= phase.wallet_cap {
+        return Err(ContractError::PhaseWalletLimit {});
+    }
+
+    PHASE_MINTED.save(storage, phase_key(phase), &(minted + 1))?;
+    PHASE_WALLET_MINTED.save(storage, (phase_key(phase), sender), &(wallet_minted + 1))?;
+    Ok(())
+}
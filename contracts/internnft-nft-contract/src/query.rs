@@ -1,21 +1,195 @@
+use std::convert::TryFrom;
+
 use cosmwasm_std::{to_binary, Binary, BlockInfo, Deps, Empty, Env, Order, StdError, StdResult};
-use cw721::{NumTokensResponse, OwnerOfResponse, TokensResponse};
-use cw721_base::{msg::QueryMsg as Cw721QueryMsg, Cw721Contract};
-use cw_storage_plus::Bound;
+use cw721::{Expiration, NumTokensResponse, OwnerOfResponse};
+use cw721_base::msg::{MinterResponse, QueryMsg as Cw721QueryMsg};
+use cw721_base::Cw721Contract;
+use cw_storage_plus::{Bound, MultiIndex, PrimaryKey, U64Key};
 use internnft::nft::{
-    full_token_id, numeric_token_id, Config, Cw721AllNftInfoResponse, Cw721NftInfoResponse,
-    InternExtension, InternTokenInfo, InternTokensResponse, QueryMsg,
+    Config, Cw721AllNftInfoResponse, Cw721ApprovalResponse, Cw721ApprovalsResponse,
+    Cw721NftInfoResponse, Cw721TokensResponse, ExportStateResponse, ExportedToken, InternExtension,
+    InternNftInfoResponse, InternTokenInfo, InternTokenStakingInfo, InternTokensResponse,
+    InternTokensStakingResponse, MetadataConfig, MintPhaseKind, MintStatusResponse, OrderBy,
+    OwnerResponse, PauseFlags, QueryMsg, StartingTraitsPreviewResponse, TraitFilter, TraitRanges,
+    TraitUpdater, TraitUpdatersResponse, WalletLimitExemptionsResponse,
 };
+use internnft::staking::{QueryMsg as StakingQueryMsg, StakingInfo};
+use internnft::token_id::TokenId;
 
-use crate::state::{tokens, CONFIG};
+use crate::phases::{phase_minted, phase_wallet_minted};
+use crate::state::{
+    import_sealed, metadata_config, minted_count, owner_token_count, pause_flags, token_prefix,
+    tokens, TraitIndexKey, CONFIG, OWNER, OWNERSHIP_PROPOSAL, TRAIT_UPDATERS,
+    WALLET_LIMIT_EXEMPTIONS,
+};
+use crate::traits::{mint_seed, roll_traits};
 
 pub fn query_config(deps: Deps) -> StdResult<Config> {
     CONFIG.load(deps.storage)
 }
 
-pub fn query_intern_nft_info(deps: Deps, token_id: String) -> StdResult<InternTokenInfo> {
-    let token = tokens().load(deps.storage, &token_id)?;
-    Ok(token)
+pub fn query_mint_status(
+    deps: Deps,
+    env: Env,
+    address: Option<String>,
+) -> StdResult<MintStatusResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let minted = minted_count(deps.storage)?;
+    let remaining_supply = config.token_supply.saturating_sub(minted);
+    let wallet = address
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    let wallet_remaining = match &wallet {
+        Some(wallet) => {
+            let held = query_num_tokens_for_owner(deps, wallet.to_string())?.count;
+            Some((config.wallet_limit as u64).saturating_sub(held) as u32)
+        }
+        None => None,
+    };
+
+    if config.mint_phases.is_empty() {
+        return Ok(MintStatusResponse {
+            phase: match config.presale {
+                true => MintPhaseKind::Allowlist,
+                false => MintPhaseKind::Public,
+            },
+            phase_index: None,
+            phase_end: None,
+            price: Some(config.mint_fee),
+            minted,
+            remaining_supply,
+            phase_remaining_supply: None,
+            wallet_remaining,
+        });
+    }
+
+    match config.active_phase(&env.block) {
+        Some((index, phase)) if phase.kind != MintPhaseKind::Closed => {
+            let phase_wallet_remaining = match &wallet {
+                Some(wallet) => Some(phase.wallet_cap.saturating_sub(phase_wallet_minted(
+                    deps.storage,
+                    phase,
+                    wallet,
+                )?)),
+                None => None,
+            };
+            Ok(MintStatusResponse {
+                phase: phase.kind,
+                phase_index: Some(index as u32),
+                phase_end: Some(phase.end),
+                price: Some(phase.price.clone()),
+                minted,
+                remaining_supply,
+                phase_remaining_supply: Some(
+                    phase
+                        .supply_cap
+                        .saturating_sub(phase_minted(deps.storage, phase)?),
+                ),
+                wallet_remaining: wallet_remaining
+                    .zip(phase_wallet_remaining)
+                    .map(|(a, b)| a.min(b)),
+            })
+        }
+        active => Ok(MintStatusResponse {
+            phase: MintPhaseKind::Closed,
+            phase_index: active.map(|(index, _)| index as u32),
+            phase_end: active.map(|(_, phase)| phase.end),
+            price: None,
+            minted,
+            remaining_supply,
+            phase_remaining_supply: None,
+            wallet_remaining: wallet_remaining.map(|_| 0),
+        }),
+    }
+}
+
+const DEFAULT_PREVIEW_SAMPLES: u32 = 10;
+const MAX_PREVIEW_SAMPLES: u32 = 100;
+
+pub fn query_starting_traits_preview(
+    deps: Deps,
+    env: Env,
+    ranges: Option<TraitRanges>,
+    samples: Option<u32>,
+) -> StdResult<StartingTraitsPreviewResponse> {
+    let ranges = match ranges {
+        Some(ranges) => ranges,
+        None => CONFIG.load(deps.storage)?.starting_traits,
+    };
+    ranges.validate()?;
+
+    let samples = samples
+        .unwrap_or(DEFAULT_PREVIEW_SAMPLES)
+        .min(MAX_PREVIEW_SAMPLES);
+    // each sample is rolled like a mint of token i by the contract itself
+    let sampler = env.contract.address.clone();
+    let samples = (1..=samples)
+        .map(|i| roll_traits(&ranges, &mint_seed(&env, &sampler, &i.to_string())))
+        .collect();
+    Ok(StartingTraitsPreviewResponse { ranges, samples })
+}
+
+pub fn query_owner(deps: Deps) -> StdResult<OwnerResponse> {
+    Ok(OwnerResponse {
+        owner: OWNER.load(deps.storage)?,
+        proposal: OWNERSHIP_PROPOSAL.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_metadata_config(deps: Deps) -> StdResult<MetadataConfig> {
+    metadata_config(deps.storage)
+}
+
+pub fn query_pause_status(deps: Deps) -> StdResult<PauseFlags> {
+    pause_flags(deps.storage)
+}
+
+pub fn query_intern_nft_info(deps: Deps, token_id: TokenId) -> StdResult<InternNftInfoResponse> {
+    load_with_metadata(deps, token_id)
+}
+
+pub fn query_intern_nft_staking_info(
+    deps: Deps,
+    token_id: TokenId,
+) -> StdResult<InternTokenStakingInfo> {
+    let staking_contract = CONFIG.load(deps.storage)?.staking_contract;
+    Ok(InternTokenStakingInfo {
+        token: load_with_metadata(deps, token_id)?,
+        staking: query_staking_info(deps, &staking_contract, token_id)?,
+    })
+}
+
+// the staking contract brings the stored stamina up to the current block
+fn query_staking_info(
+    deps: Deps,
+    staking_contract: &str,
+    token_id: TokenId,
+) -> StdResult<Option<StakingInfo>> {
+    deps.querier.query_wasm_smart(
+        staking_contract,
+        &StakingQueryMsg::GetStakingInfo { token_id },
+    )
+}
+
+fn load_with_metadata(deps: Deps, token_id: TokenId) -> StdResult<InternNftInfoResponse> {
+    let token = tokens().load(deps.storage, &token_id.full(&token_prefix(deps.storage)?))?;
+    Ok(token.into_response(&token_id.to_string(), &metadata_config(deps.storage)?))
+}
+
+// fills the collection-wide metadata settings into a page of tokens
+fn with_metadata(
+    deps: Deps,
+    prefix: &str,
+    tokens: Vec<InternTokenInfo>,
+) -> StdResult<Vec<InternNftInfoResponse>> {
+    let metadata = metadata_config(deps.storage)?;
+    tokens
+        .into_iter()
+        .map(|token| {
+            let token_id = token_id_of(prefix, &token.name)?;
+            Ok(token.into_response(&token_id.to_string(), &metadata))
+        })
+        .collect()
 }
 
 const DEFAULT_LIMIT: u32 = 10;
@@ -24,105 +198,448 @@ const MAX_LIMIT: u32 = 30;
 pub fn query_intern_tokens(
     deps: Deps,
     owner: String,
-    start_after: Option<String>,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
     limit: Option<u32>,
+    order: Option<OrderBy>,
 ) -> StdResult<InternTokensResponse> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after.map(Bound::exclusive);
+    let prefix = token_prefix(deps.storage)?;
+    let (min, max) = token_bounds(&prefix, start_after, start_before);
 
     let owner = deps.api.addr_validate(&owner)?;
-    let tokens: StdResult<Vec<_>> = tokens()
+    let tokens = tokens()
         .idx
-        .owner
-        .prefix(owner)
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(|(_, token)| token))
+        .owner_number
+        .sub_prefix(owner)
+        .range(deps.storage, min, max, order_or_default(order))
+        .map(|item| item.map(|(_, token)| token));
+    let (tokens, next_cursor) = paginate(tokens, limit, |token| token_id_of(&prefix, &token.name))?;
+    Ok(InternTokensResponse {
+        tokens: with_metadata(deps, &prefix, tokens)?,
+        next_cursor,
+    })
+}
+
+pub fn query_intern_tokens_staking_info(
+    deps: Deps,
+    owner: String,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<InternTokensStakingResponse> {
+    let staking_contract = CONFIG.load(deps.storage)?.staking_contract;
+    let prefix = token_prefix(deps.storage)?;
+
+    let page = query_intern_tokens(deps, owner, start_after, start_before, limit, order)?;
+    let tokens: StdResult<Vec<_>> = page
+        .tokens
+        .into_iter()
+        .map(|token| {
+            Ok(InternTokenStakingInfo {
+                staking: query_staking_info(
+                    deps,
+                    &staking_contract,
+                    token_id_of(&prefix, &token.name)?,
+                )?,
+                token,
+            })
+        })
         .collect();
-    Ok(InternTokensResponse { tokens: tokens? })
+    Ok(InternTokensStakingResponse {
+        tokens: tokens?,
+        next_cursor: page.next_cursor,
+    })
 }
 
 pub fn query_all_intern_tokens(
     deps: Deps,
-    start_after: Option<String>,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
     limit: Option<u32>,
+    order: Option<OrderBy>,
 ) -> StdResult<InternTokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = token_prefix(deps.storage)?;
+    let (min, max) = token_bounds(&prefix, start_after, start_before);
+
+    let tokens = tokens();
+    let tokens = tokens
+        .idx
+        .number
+        .range(deps.storage, min, max, order_or_default(order))
+        .map(|item| item.map(|(_, token)| token));
+    let (tokens, next_cursor) = paginate(tokens, limit, |token| token_id_of(&prefix, &token.name))?;
+    Ok(InternTokensResponse {
+        tokens: with_metadata(deps, &prefix, tokens)?,
+        next_cursor,
+    })
+}
+
+pub fn query_export_state(
+    deps: Deps,
+    start_after: Option<TokenId>,
+    limit: Option<u32>,
+) -> StdResult<ExportStateResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = token_prefix(deps.storage)?;
+    let (min, _) = token_bounds(&prefix, start_after, None);
+
+    let tokens = tokens();
+    let tokens = tokens
+        .idx
+        .number
+        .range(deps.storage, min, None, Order::Ascending)
+        .map(|item| {
+            let (key, token) = item?;
+            Ok(ExportedToken {
+                token_id: numeric_key(&prefix, key)?,
+                token,
+            })
+        });
+    let (tokens, next_cursor) = paginate(tokens, limit, |exported| Ok(exported.token_id))?;
+    Ok(ExportStateResponse {
+        minted: minted_count(deps.storage)?,
+        import_sealed: import_sealed(deps.storage)?,
+        tokens,
+        next_cursor,
+    })
+}
+
+fn order_or_default(order: Option<OrderBy>) -> Order {
+    order.unwrap_or(OrderBy::Ascending).into()
+}
+
+// bounds for the `number` index, or the `owner_number` index within one owner
+fn token_bounds(
+    prefix: &str,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
+) -> (Option<Bound>, Option<Bound>) {
+    (
+        start_after.map(|id| number_bound(prefix, id)),
+        start_before.map(|id| number_bound(prefix, id)),
+    )
+}
+
+fn number_bound(prefix: &str, token_id: TokenId) -> Bound {
+    let key = (
+        U64Key::new(token_id.u64()),
+        token_id.full(prefix).into_bytes(),
+    );
+    Bound::exclusive(key.joined_key())
+}
+
+// tokens are stored under their name
+fn token_id_of(prefix: &str, name: &str) -> StdResult<TokenId> {
+    TokenId::from_full(prefix, name)
+}
+
+// takes one item past the page, so the last page comes without a cursor
+fn paginate<T>(
+    items: impl Iterator<Item = StdResult<T>>,
+    limit: usize,
+    cursor: impl Fn(&T) -> StdResult<TokenId>,
+) -> StdResult<(Vec<T>, Option<TokenId>)> {
+    let mut page = items.take(limit + 1).collect::<StdResult<Vec<_>>>()?;
+    if page.len() <= limit {
+        return Ok((page, None));
+    }
+    page.truncate(limit);
+    let next_cursor = page.last().map(cursor).transpose()?;
+    Ok((page, next_cursor))
+}
+
+// tokens a search reads per call, so that filters matching few tokens stay within the query gas
+// limit
+const MAX_SEARCH_SCAN: usize = 300;
+
+pub fn query_search_tokens(
+    deps: Deps,
+    owner: Option<String>,
+    filter: TraitFilter,
+    start_after: Option<TokenId>,
+    limit: Option<u32>,
+) -> StdResult<InternTokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = token_prefix(deps.storage)?;
+
+    let tokens = tokens();
+    let candidates = match owner {
+        Some(owner) => {
+            let owner = deps.api.addr_validate(&owner)?;
+            let (start, _) = token_bounds(&prefix, start_after, None);
+            tokens.idx.owner_number.sub_prefix(owner).range(
+                deps.storage,
+                start,
+                None,
+                Order::Ascending,
+            )
+        }
+        // only the tokens within the experience bounds are read
+        None if filter.min_experience.is_some() || filter.max_experience.is_some() => {
+            let start = match start_after {
+                Some(token_id) => {
+                    let full_id = token_id.full(&prefix);
+                    let token = tokens.load(deps.storage, &full_id)?;
+                    experience_bound(token.extension.experience, token_id.u64(), full_id, false)
+                }
+                None => {
+                    experience_bound(filter.min_experience.unwrap_or(0), 0, String::new(), true)
+                }
+            };
+            let end = filter
+                .max_experience
+                .and_then(|max| max.checked_add(1))
+                .map(|end| experience_bound(end, 0, String::new(), true));
+            tokens
+                .idx
+                .experience
+                .range(deps.storage, Some(start), end, Order::Ascending)
+        }
+        None => {
+            let (start, _) = token_bounds(&prefix, start_after, None);
+            tokens
+                .idx
+                .number
+                .range(deps.storage, start, None, Order::Ascending)
+        }
+    };
+
+    let mut page = vec![];
+    let mut last_scanned = None;
+    let mut exhausted = true;
+    for (scanned, item) in candidates.enumerate() {
+        if scanned == MAX_SEARCH_SCAN || page.len() == limit {
+            exhausted = false;
+            break;
+        }
+        let (_, token) = item?;
+        last_scanned = Some(token_id_of(&prefix, &token.name)?);
+        if filter.matches(&token.extension) {
+            page.push(token);
+        }
+    }
+    Ok(InternTokensResponse {
+        tokens: with_metadata(deps, &prefix, page)?,
+        next_cursor: if exhausted { None } else { last_scanned },
+    })
+}
+
+fn experience_bound(experience: u64, number: u64, full_id: String, inclusive: bool) -> Bound {
+    let key: TraitIndexKey = (
+        U64Key::new(experience),
+        (U64Key::new(number), full_id.into_bytes()),
+    );
+    if inclusive {
+        Bound::inclusive(key.joined_key())
+    } else {
+        Bound::exclusive(key.joined_key())
+    }
+}
+
+pub fn query_top_by_experience(
+    deps: Deps,
+    start_after: Option<TokenId>,
+    limit: Option<u32>,
+) -> StdResult<InternTokensResponse> {
+    let tokens = tokens();
+    leaderboard(
+        deps,
+        &tokens.idx.experience,
+        |traits| traits.experience,
+        start_after,
+        limit,
+    )
+}
+
+pub fn query_top_by_gold(
+    deps: Deps,
+    start_after: Option<TokenId>,
+    limit: Option<u32>,
+) -> StdResult<InternTokensResponse> {
+    let tokens = tokens();
+    leaderboard(
+        deps,
+        &tokens.idx.gold,
+        |traits| traits.gold,
+        start_after,
+        limit,
+    )
+}
+
+// walks a trait index from the highest value down. Pages continue below the current value of
+// the `start_after` token, so they can shift if it changes in between.
+fn leaderboard(
+    deps: Deps,
+    index: &MultiIndex<TraitIndexKey, InternTokenInfo>,
+    value: fn(&InternExtension) -> u64,
+    start_after: Option<TokenId>,
+    limit: Option<u32>,
+) -> StdResult<InternTokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = token_prefix(deps.storage)?;
+    let end = match start_after {
+        Some(token_id) => {
+            let full_id = token_id.full(&prefix);
+            let token = tokens().load(deps.storage, &full_id)?;
+            let key = (
+                U64Key::new(value(&token.extension)),
+                (U64Key::new(token_id.u64()), full_id.into_bytes()),
+            );
+            Some(Bound::exclusive(index.index_key(key)))
+        }
+        None => None,
+    };
+
+    let tokens = index
+        .range(deps.storage, None, end, Order::Descending)
+        .map(|item| item.map(|(_, token)| token));
+    let (tokens, next_cursor) = paginate(tokens, limit, |token| token_id_of(&prefix, &token.name))?;
+    Ok(InternTokensResponse {
+        tokens: with_metadata(deps, &prefix, tokens)?,
+        next_cursor,
+    })
+}
+
+pub fn query_trait_updaters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TraitUpdatersResponse> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
     let start = start_after.map(Bound::exclusive);
 
-    let tokens: StdResult<Vec<_>> = tokens()
+    let updaters: StdResult<Vec<_>> = TRAIT_UPDATERS
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
-        .map(|item| item.map(|(_, token)| token))
+        .map(|item| {
+            item.map(|(address, traits)| TraitUpdater {
+                address: String::from_utf8_lossy(&address).into_owned(),
+                traits,
+            })
+        })
+        .collect();
+    Ok(TraitUpdatersResponse {
+        updaters: updaters?,
+    })
+}
+
+pub fn query_wallet_limit_exemptions(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WalletLimitExemptionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let addresses: StdResult<Vec<_>> = WALLET_LIMIT_EXEMPTIONS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|address| String::from_utf8(address).map_err(StdError::invalid_utf8))
         .collect();
-    Ok(InternTokensResponse { tokens: tokens? })
+    Ok(WalletLimitExemptionsResponse {
+        addresses: addresses?,
+    })
 }
 
 pub fn query_num_tokens_for_owner(deps: Deps, owner: String) -> StdResult<NumTokensResponse> {
     let owner = deps.api.addr_validate(&owner)?;
-    let count = tokens()
-        .idx
-        .owner
-        .prefix(owner)
-        .range(deps.storage, None, None, Order::Ascending)
-        .count() as u64;
+    let count = owner_token_count(deps.storage, &owner)?;
     Ok(NumTokensResponse { count })
 }
 
 pub fn cw721_base_query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let cw721_contract = Cw721Contract::<InternExtension, Empty>::default();
 
-    let cw721_msg: Cw721QueryMsg = msg.into();
-    match cw721_msg {
-        Cw721QueryMsg::NftInfo { token_id } => {
-            to_binary(&query_nft_info(deps, env, full_token_id(token_id)?)?)
-        }
-        Cw721QueryMsg::AllNftInfo {
+    match msg {
+        QueryMsg::NftInfo { token_id } => to_binary(&query_nft_info(deps, env, token_id)?),
+        QueryMsg::AllNftInfo {
             token_id,
             include_expired,
         } => to_binary(&query_all_nft_info(
             deps,
             env,
-            full_token_id(token_id)?,
+            token_id,
             include_expired.unwrap_or(false),
         )?),
-        Cw721QueryMsg::Tokens {
+        QueryMsg::Tokens {
             owner,
             start_after,
+            start_before,
             limit,
-        } => to_binary(&query_tokens(deps, owner, start_after, limit)?),
-        Cw721QueryMsg::AllTokens { start_after, limit } => {
-            to_binary(&query_all_tokens(deps, start_after, limit)?)
-        }
-        Cw721QueryMsg::OwnerOf {
+            order,
+        } => to_binary(&query_tokens(
+            deps,
+            owner,
+            start_after,
+            start_before,
+            limit,
+            order,
+        )?),
+        QueryMsg::AllTokens {
+            start_after,
+            start_before,
+            limit,
+            order,
+        } => to_binary(&query_all_tokens(
+            deps,
+            start_after,
+            start_before,
+            limit,
+            order,
+        )?),
+        QueryMsg::OwnerOf {
             token_id,
             include_expired,
         } => to_binary(&owner_of(
             deps,
             env,
-            full_token_id(token_id)?,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Approval {
+            token_id,
+            spender,
+            include_expired,
+        } => to_binary(&query_approval(
+            deps,
+            env,
+            token_id,
+            spender,
             include_expired.unwrap_or(false),
         )?),
-        _ => cw721_contract.query(deps, env, cw721_msg),
+        QueryMsg::Approvals {
+            token_id,
+            include_expired,
+        } => to_binary(&query_approvals(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Minter {} => to_binary(&query_minter(deps)?),
+        _ => cw721_contract.query(deps, env, Cw721QueryMsg::try_from(msg)?),
     }
 }
 
-pub fn query_nft_info(deps: Deps, _env: Env, token_id: String) -> StdResult<Cw721NftInfoResponse> {
-    let info = tokens().load(deps.storage, &token_id)?;
+pub fn query_nft_info(deps: Deps, _env: Env, token_id: TokenId) -> StdResult<Cw721NftInfoResponse> {
+    let info = load_with_metadata(deps, token_id)?;
     Ok(info.as_cw721_nft_info())
 }
 
 pub fn query_all_nft_info(
     deps: Deps,
     env: Env,
-    token_id: String,
+    token_id: TokenId,
     include_expired: bool,
 ) -> StdResult<Cw721AllNftInfoResponse> {
-    let info = tokens().load(deps.storage, &token_id)?;
+    let info = load_with_metadata(deps, token_id)?;
     Ok(Cw721AllNftInfoResponse {
         access: OwnerOfResponse {
             owner: info.owner.to_string(),
-            approvals: humanize_approvals(&env.block, &info, include_expired),
+            approvals: humanize_approvals(&env.block, &info.approvals, include_expired),
         },
         info: info.as_cw721_nft_info(),
     })
@@ -131,75 +648,121 @@ pub fn query_all_nft_info(
 pub fn query_tokens(
     deps: Deps,
     owner: String,
-    start_after: Option<String>,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
     limit: Option<u32>,
-) -> StdResult<TokensResponse> {
+    order: Option<OrderBy>,
+) -> StdResult<Cw721TokensResponse> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after.map(Bound::exclusive);
+    let prefix = token_prefix(deps.storage)?;
+    let (min, max) = token_bounds(&prefix, start_after, start_before);
 
     let owner_addr = deps.api.addr_validate(&owner)?;
-    let pks: Vec<_> = tokens()
+    let token_ids = tokens()
         .idx
-        .owner
-        .prefix(owner_addr)
-        .keys(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .collect();
-
-    let res: Result<Vec<_>, _> = pks.iter().map(|v| String::from_utf8(v.to_vec())).collect();
-    let tokens = res.map_err(StdError::invalid_utf8)?;
-    let numeric_tokens: Vec<String> = tokens
-        .iter()
-        .map(|s| numeric_token_id(s.to_string()).unwrap())
-        .collect();
-    Ok(TokensResponse {
-        tokens: numeric_tokens,
+        .owner_number
+        .sub_prefix(owner_addr)
+        .range(deps.storage, min, max, order_or_default(order))
+        .map(|item| item.and_then(|(key, _)| numeric_key(&prefix, key)));
+    let (token_ids, next_cursor) = paginate(token_ids, limit, |id| Ok(*id))?;
+    Ok(Cw721TokensResponse {
+        tokens: token_ids.iter().map(TokenId::to_string).collect(),
+        next_cursor,
     })
 }
 
 pub fn query_all_tokens(
     deps: Deps,
-    start_after: Option<String>,
+    start_after: Option<TokenId>,
+    start_before: Option<TokenId>,
     limit: Option<u32>,
-) -> StdResult<TokensResponse> {
+    order: Option<OrderBy>,
+) -> StdResult<Cw721TokensResponse> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after.map(Bound::exclusive);
+    let prefix = token_prefix(deps.storage)?;
+    let (min, max) = token_bounds(&prefix, start_after, start_before);
 
-    let res: StdResult<Vec<String>> = tokens()
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
-        .collect();
-    let tokens = res.map_err(StdError::invalid_utf8)?;
-    let numeric_tokens: Vec<String> = tokens
-        .iter()
-        .map(|s| numeric_token_id(s.to_string()).unwrap())
-        .collect();
-    Ok(TokensResponse {
-        tokens: numeric_tokens,
+    let tokens = tokens();
+    let token_ids = tokens
+        .idx
+        .number
+        .range(deps.storage, min, max, order_or_default(order))
+        .map(|item| item.and_then(|(key, _)| numeric_key(&prefix, key)));
+    let (token_ids, next_cursor) = paginate(token_ids, limit, |id| Ok(*id))?;
+    Ok(Cw721TokensResponse {
+        tokens: token_ids.iter().map(TokenId::to_string).collect(),
+        next_cursor,
     })
 }
 
+fn numeric_key(prefix: &str, key: Vec<u8>) -> StdResult<TokenId> {
+    let full_id = String::from_utf8(key).map_err(StdError::invalid_utf8)?;
+    TokenId::from_full(prefix, &full_id)
+}
+
 pub fn owner_of(
     deps: Deps,
     env: Env,
-    token_id: String,
+    token_id: TokenId,
     include_expired: bool,
 ) -> StdResult<OwnerOfResponse> {
-    let info = tokens().load(deps.storage, &token_id)?;
+    let info = tokens().load(deps.storage, &token_id.full(&token_prefix(deps.storage)?))?;
     Ok(OwnerOfResponse {
         owner: info.owner.to_string(),
-        approvals: humanize_approvals(&env.block, &info, include_expired),
+        approvals: humanize_approvals(&env.block, &info.approvals, include_expired),
+    })
+}
+
+pub fn query_approval(
+    deps: Deps,
+    env: Env,
+    token_id: TokenId,
+    spender: String,
+    include_expired: bool,
+) -> StdResult<Cw721ApprovalResponse> {
+    let info = tokens().load(deps.storage, &token_id.full(&token_prefix(deps.storage)?))?;
+    if info.owner == spender {
+        return Ok(Cw721ApprovalResponse {
+            approval: cw721::Approval {
+                spender,
+                expires: Expiration::Never {},
+            },
+        });
+    }
+
+    humanize_approvals(&env.block, &info.approvals, include_expired)
+        .into_iter()
+        .find(|approval| approval.spender == spender)
+        .map(|approval| Cw721ApprovalResponse { approval })
+        .ok_or_else(|| StdError::not_found("Approval"))
+}
+
+pub fn query_approvals(
+    deps: Deps,
+    env: Env,
+    token_id: TokenId,
+    include_expired: bool,
+) -> StdResult<Cw721ApprovalsResponse> {
+    let info = tokens().load(deps.storage, &token_id.full(&token_prefix(deps.storage)?))?;
+    Ok(Cw721ApprovalsResponse {
+        approvals: humanize_approvals(&env.block, &info.approvals, include_expired),
+    })
+}
+
+// cw721-base never stores a minter here, since only the contract owner can batch mint
+pub fn query_minter(deps: Deps) -> StdResult<MinterResponse> {
+    Ok(MinterResponse {
+        minter: OWNER.load(deps.storage)?,
     })
 }
 
 // adapted from: https://github.com/CosmWasm/cw-nfts/blob/5e1e72a3682f988d4504b94f2e203dd4a5a99ad9/contracts/cw721-base/src/query.rs#L211-L228
 fn humanize_approvals(
     block: &BlockInfo,
-    info: &InternTokenInfo,
+    approvals: &[cw721_base::state::Approval],
     include_expired: bool,
 ) -> Vec<cw721::Approval> {
-    info.approvals
+    approvals
         .iter()
         .filter(|apr| include_expired || !apr.is_expired(block))
         .map(humanize_approval)
@@ -218,7 +781,8 @@ mod test {
     use super::*;
 
     use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{from_binary, Addr, DepsMut};
+    use cosmwasm_std::{from_binary, from_slice, Addr, DepsMut};
+    use cw721::TokensResponse;
     use internnft::nft::{Cw721AllNftInfoResponse, Cw721Metadata, Cw721Trait};
 
     const ADDR1: &str = "addr1";
@@ -259,8 +823,14 @@ mod test {
         }
     }
 
-    fn numeric_id_error() -> StdError {
-        StdError::generic_err("expected numeric token identifier")
+    fn assert_rejects_full_id(json: &[u8]) {
+        let err = from_slice::<QueryMsg>(json).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected numeric token identifier"),
+            "{}",
+            err
+        );
     }
 
     #[test]
@@ -268,11 +838,15 @@ mod test {
         let mut deps = mock_dependencies(&[]);
         setup_storage(deps.as_mut());
 
+        let image_data = token_examples()[0]
+            .clone()
+            .into_response("1", &MetadataConfig::default())
+            .image_data();
         let expected = Cw721NftInfoResponse {
             token_uri: None,
             extension: Cw721Metadata {
-                image: None,
-                image_data: None,
+                image: Some(image_data.clone()),
+                image_data: Some(image_data),
                 external_url: None,
                 description: Some("".to_string()),
                 name: Some("intern #1".to_string()),
@@ -300,15 +874,7 @@ mod test {
         };
 
         // nft_info blocks full token identifiers
-        let err = cw721_base_query(
-            deps.as_ref(),
-            mock_env(),
-            QueryMsg::NftInfo {
-                token_id: "intern #1".to_string(),
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"nft_info":{"token_id":"intern #1"}}"#);
 
         // nft_info looks up token info for numeric id
         let info = from_binary::<Cw721NftInfoResponse>(
@@ -316,7 +882,7 @@ mod test {
                 deps.as_ref(),
                 mock_env(),
                 QueryMsg::NftInfo {
-                    token_id: "1".to_string(),
+                    token_id: TokenId::new(1),
                 },
             )
             .unwrap(),
@@ -325,16 +891,7 @@ mod test {
         assert_eq!(info, expected);
 
         // all_nft_info blocks full token identifiers
-        let err = cw721_base_query(
-            deps.as_ref(),
-            mock_env(),
-            QueryMsg::AllNftInfo {
-                token_id: "intern #1".to_string(),
-                include_expired: None,
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"all_nft_info":{"token_id":"intern #1"}}"#);
 
         // all_nft_info looks up token access and info for numeric id
         let all_info = from_binary::<Cw721AllNftInfoResponse>(
@@ -342,7 +899,7 @@ mod test {
                 deps.as_ref(),
                 mock_env(),
                 QueryMsg::AllNftInfo {
-                    token_id: "1".to_string(),
+                    token_id: TokenId::new(1),
                     include_expired: None,
                 },
             )
@@ -374,7 +931,9 @@ mod test {
                 QueryMsg::Tokens {
                     owner: ADDR1.to_string(),
                     start_after: None,
+                    start_before: None,
                     limit: None,
+                    order: None,
                 },
             )
             .unwrap(),
@@ -389,7 +948,9 @@ mod test {
                 mock_env(),
                 QueryMsg::AllTokens {
                     start_after: None,
+                    start_before: None,
                     limit: None,
+                    order: None,
                 },
             )
             .unwrap(),
@@ -398,22 +959,169 @@ mod test {
         assert_eq!(res.tokens, vec!["1".to_string(), "2".to_string()]);
     }
 
+    #[test]
+    fn paginate_tokens() {
+        let mut deps = mock_dependencies(&[]);
+        for i in 1..=5 {
+            let mut token = token_examples()[0].clone();
+            token.name = format!("intern #{}", i);
+            tokens()
+                .save(deps.as_mut().storage, &token.name, &token)
+                .unwrap();
+        }
+
+        let all_tokens = |start_after: Option<u64>,
+                          start_before: Option<u64>,
+                          limit: Option<u32>,
+                          order: Option<OrderBy>| {
+            query_all_tokens(
+                deps.as_ref(),
+                start_after.map(TokenId::new),
+                start_before.map(TokenId::new),
+                limit,
+                order,
+            )
+            .unwrap()
+        };
+
+        // the cursor points at the last token of a full page
+        let res = all_tokens(None, None, Some(2), None);
+        assert_eq!(res.tokens, vec!["1", "2"]);
+        assert_eq!(res.next_cursor, Some(TokenId::new(2)));
+        let res = all_tokens(Some(2), None, Some(2), None);
+        assert_eq!(res.tokens, vec!["3", "4"]);
+        assert_eq!(res.next_cursor, Some(TokenId::new(4)));
+        // and is unset on the last page, even if it is full
+        let res = all_tokens(Some(4), None, Some(1), None);
+        assert_eq!(res.tokens, vec!["5"]);
+        assert_eq!(res.next_cursor, None);
+
+        // descending pages continue with start_before
+        let res = all_tokens(None, None, Some(2), Some(OrderBy::Descending));
+        assert_eq!(res.tokens, vec!["5", "4"]);
+        assert_eq!(res.next_cursor, Some(TokenId::new(4)));
+        let res = all_tokens(None, Some(4), Some(2), Some(OrderBy::Descending));
+        assert_eq!(res.tokens, vec!["3", "2"]);
+        let res = all_tokens(None, Some(2), None, Some(OrderBy::Descending));
+        assert_eq!(res.tokens, vec!["1"]);
+        assert_eq!(res.next_cursor, None);
+
+        // both bounds together
+        let res = all_tokens(Some(1), Some(5), None, Some(OrderBy::Descending));
+        assert_eq!(res.tokens, vec!["4", "3", "2"]);
+
+        // the intern token lists page the same way
+        let res = query_intern_tokens(
+            deps.as_ref(),
+            ADDR1.to_string(),
+            None,
+            Some(TokenId::new(3)),
+            Some(1),
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(res.tokens[0].name, "intern #2");
+        assert_eq!(res.next_cursor, Some(TokenId::new(2)));
+        let res = query_all_intern_tokens(deps.as_ref(), Some(TokenId::new(3)), None, None, None)
+            .unwrap();
+        let names: Vec<_> = res.tokens.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["intern #4", "intern #5"]);
+        assert_eq!(res.next_cursor, None);
+    }
+
+    #[test]
+    fn tokens_are_ordered_by_number() {
+        let mut deps = mock_dependencies(&[]);
+        for i in 1..=12 {
+            let mut token = token_examples()[0].clone();
+            token.name = format!("intern #{}", i);
+            tokens()
+                .save(deps.as_mut().storage, &token.name, &token)
+                .unwrap();
+        }
+
+        let res = query_all_tokens(
+            deps.as_ref(),
+            None,
+            None,
+            Some(4),
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(res.tokens, vec!["12", "11", "10", "9"]);
+        let res = query_all_tokens(
+            deps.as_ref(),
+            None,
+            res.next_cursor,
+            Some(4),
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(res.tokens, vec!["8", "7", "6", "5"]);
+
+        let res =
+            query_all_tokens(deps.as_ref(), Some(TokenId::new(8)), None, Some(3), None).unwrap();
+        assert_eq!(res.tokens, vec!["9", "10", "11"]);
+
+        let res = query_tokens(
+            deps.as_ref(),
+            ADDR1.to_string(),
+            Some(TokenId::new(2)),
+            Some(TokenId::new(12)),
+            None,
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(
+            res.tokens,
+            vec!["11", "10", "9", "8", "7", "6", "5", "4", "3"]
+        );
+
+        let names = |res: InternTokensResponse| -> Vec<String> {
+            res.tokens.into_iter().map(|token| token.name).collect()
+        };
+        let res = query_intern_tokens(
+            deps.as_ref(),
+            ADDR1.to_string(),
+            None,
+            None,
+            Some(4),
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(
+            names(res),
+            vec!["intern #12", "intern #11", "intern #10", "intern #9"]
+        );
+        let res = query_all_intern_tokens(
+            deps.as_ref(),
+            None,
+            Some(TokenId::new(3)),
+            None,
+            Some(OrderBy::Descending),
+        )
+        .unwrap();
+        assert_eq!(names(res), vec!["intern #2", "intern #1"]);
+        let res = query_search_tokens(
+            deps.as_ref(),
+            None,
+            TraitFilter::default(),
+            Some(TokenId::new(9)),
+            None,
+        )
+        .unwrap();
+        assert_eq!(names(res), vec!["intern #10", "intern #11", "intern #12"]);
+        let res = query_export_state(deps.as_ref(), Some(TokenId::new(9)), Some(1)).unwrap();
+        assert_eq!(res.tokens[0].token_id, TokenId::new(10));
+    }
+
     #[test]
     fn owner_of() {
         let mut deps = mock_dependencies(&[]);
         setup_storage(deps.as_mut());
 
         // owner_of blocks full token identifiers
-        let err = cw721_base_query(
-            deps.as_ref(),
-            mock_env(),
-            QueryMsg::OwnerOf {
-                token_id: "intern #1".to_string(),
-                include_expired: None,
-            },
-        )
-        .unwrap_err();
-        assert_eq!(err, numeric_id_error());
+        assert_rejects_full_id(br#"{"owner_of":{"token_id":"intern #1"}}"#);
 
         // owner_of looks up token ownership for numeric id
         let res = from_binary::<OwnerOfResponse>(
@@ -421,7 +1129,7 @@ mod test {
                 deps.as_ref(),
                 mock_env(),
                 QueryMsg::OwnerOf {
-                    token_id: "1".to_string(),
+                    token_id: TokenId::new(1),
                     include_expired: None,
                 },
             )
@@ -441,7 +1149,7 @@ mod test {
                 deps.as_ref(),
                 mock_env(),
                 QueryMsg::OwnerOf {
-                    token_id: "2".to_string(),
+                    token_id: TokenId::new(2),
                     include_expired: None,
                 },
             )
@@ -456,4 +1164,101 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn approvals() {
+        let mut deps = mock_dependencies(&[]);
+        setup_storage(deps.as_mut());
+
+        let env = mock_env();
+        let mut token = token_examples()[0].clone();
+        token.approvals = vec![
+            cw721_base::state::Approval {
+                spender: Addr::unchecked(ADDR2),
+                expires: Expiration::Never {},
+            },
+            cw721_base::state::Approval {
+                spender: Addr::unchecked("expired"),
+                expires: Expiration::AtHeight(env.block.height - 1),
+            },
+        ];
+        tokens()
+            .save(deps.as_mut().storage, &token.name, &token)
+            .unwrap();
+
+        // approval blocks full token identifiers
+        assert_rejects_full_id(br#"{"approval":{"token_id":"intern #1","spender":"addr2"}}"#);
+        assert_rejects_full_id(br#"{"approvals":{"token_id":"intern #1"}}"#);
+
+        let approval = |spender: &str, include_expired: Option<bool>| {
+            cw721_base_query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Approval {
+                    token_id: TokenId::new(1),
+                    spender: spender.to_string(),
+                    include_expired,
+                },
+            )
+            .map(|res| from_binary::<Cw721ApprovalResponse>(&res).unwrap().approval)
+        };
+
+        // the owner is always approved
+        assert_eq!(
+            approval(ADDR1, None).unwrap(),
+            cw721::Approval {
+                spender: ADDR1.to_string(),
+                expires: Expiration::Never {},
+            }
+        );
+        assert_eq!(
+            approval(ADDR2, None).unwrap(),
+            cw721::Approval {
+                spender: ADDR2.to_string(),
+                expires: Expiration::Never {},
+            }
+        );
+        // expired approvals are only returned on request
+        assert_eq!(
+            approval("expired", None).unwrap_err(),
+            StdError::not_found("Approval")
+        );
+        assert!(approval("expired", Some(true)).is_ok());
+        assert_eq!(
+            approval("stranger", Some(true)).unwrap_err(),
+            StdError::not_found("Approval")
+        );
+
+        let approvals = |include_expired: Option<bool>| {
+            from_binary::<Cw721ApprovalsResponse>(
+                &cw721_base_query(
+                    deps.as_ref(),
+                    env.clone(),
+                    QueryMsg::Approvals {
+                        token_id: TokenId::new(1),
+                        include_expired,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .approvals
+        };
+        assert_eq!(approvals(None).len(), 1);
+        assert_eq!(approvals(Some(true)).len(), 2);
+    }
+
+    #[test]
+    fn minter() {
+        let mut deps = mock_dependencies(&[]);
+        OWNER
+            .save(deps.as_mut().storage, &ADDR1.to_string())
+            .unwrap();
+
+        let res = from_binary::<MinterResponse>(
+            &cw721_base_query(deps.as_ref(), mock_env(), QueryMsg::Minter {}).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.minter, ADDR1);
+    }
 }
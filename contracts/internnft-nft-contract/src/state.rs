@@ -1,16 +1,38 @@
-use cosmwasm_std::Addr;
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
-use internnft::nft::{Config, InternTokenInfo};
+use std::str;
+
+use cosmwasm_std::{Addr, Empty, StdResult, Storage};
+use cw721_base::Cw721Contract;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, U64Key};
+use internnft::nft::{
+    Config, InternExtension, InternTokenInfo, MetadataConfig, OwnershipProposal, PauseFlags,
+    TraitKind, DEFAULT_TOKEN_PREFIX,
+};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+// trait value, then token number so ties don't sort "intern #10" before "intern #9". The number
+// is nested with the storage key, as only indexes with two-part keys can be ranged as a whole.
+pub type TraitIndexKey = (U64Key, (U64Key, Vec<u8>));
+
 pub struct TokenIndexes<'a> {
     pub owner: MultiIndex<'a, (Addr, Vec<u8>), InternTokenInfo>,
+    // token lists in numeric order, storage keys sort "intern #10" before "intern #9"
+    pub number: MultiIndex<'a, (U64Key, Vec<u8>), InternTokenInfo>,
+    pub owner_number: MultiIndex<'a, (Addr, U64Key, Vec<u8>), InternTokenInfo>,
+    // leaderboards, see `query_top_by_experience` and `query_top_by_gold`
+    pub experience: MultiIndex<'a, TraitIndexKey, InternTokenInfo>,
+    pub gold: MultiIndex<'a, TraitIndexKey, InternTokenInfo>,
 }
 
 impl<'a> IndexList<InternTokenInfo> for TokenIndexes<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<InternTokenInfo>> + '_> {
-        let v: Vec<&dyn Index<InternTokenInfo>> = vec![&self.owner];
+        let v: Vec<&dyn Index<InternTokenInfo>> = vec![
+            &self.owner,
+            &self.number,
+            &self.owner_number,
+            &self.experience,
+            &self.gold,
+        ];
         Box::new(v.into_iter())
     }
 }
@@ -22,8 +44,131 @@ pub fn tokens<'a>() -> IndexedMap<'a, &'a str, InternTokenInfo, TokenIndexes<'a>
             "tokens",
             "tokens__owner",
         ),
+        number: MultiIndex::new(
+            |_: &InternTokenInfo, k: Vec<u8>| (token_number(&k), k),
+            "tokens",
+            "tokens__number",
+        ),
+        owner_number: MultiIndex::new(
+            |d: &InternTokenInfo, k: Vec<u8>| (d.owner.clone(), token_number(&k), k),
+            "tokens",
+            "tokens__owner_number",
+        ),
+        experience: MultiIndex::new(
+            |d: &InternTokenInfo, k: Vec<u8>| {
+                (U64Key::new(d.extension.experience), (token_number(&k), k))
+            },
+            "tokens",
+            "tokens__experience",
+        ),
+        gold: MultiIndex::new(
+            |d: &InternTokenInfo, k: Vec<u8>| {
+                (U64Key::new(d.extension.gold), (token_number(&k), k))
+            },
+            "tokens",
+            "tokens__gold",
+        ),
     };
     IndexedMap::new("tokens", indexes)
 }
 
+// token ids are the prefix followed by the number, and prefixes can't end with a digit
+fn token_number(full_token_id: &[u8]) -> U64Key {
+    let digits = full_token_id
+        .iter()
+        .rev()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let number = str::from_utf8(&full_token_id[full_token_id.len() - digits..])
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .unwrap_or_default();
+    U64Key::new(number)
+}
+
 pub const OWNER: Item<String> = Item::new("owner");
+
+pub const TOKEN_PREFIX: Item<String> = Item::new("token_prefix");
+
+pub fn token_prefix(storage: &dyn Storage) -> StdResult<String> {
+    Ok(TOKEN_PREFIX
+        .may_load(storage)?
+        .unwrap_or_else(|| DEFAULT_TOKEN_PREFIX.to_string()))
+}
+
+pub const METADATA_CONFIG: Item<MetadataConfig> = Item::new("metadata_config");
+
+pub fn metadata_config(storage: &dyn Storage) -> StdResult<MetadataConfig> {
+    // contracts instantiated before the metadata settings existed have none
+    Ok(METADATA_CONFIG.may_load(storage)?.unwrap_or_default())
+}
+
+pub const PAUSE_FLAGS: Item<PauseFlags> = Item::new("pause_flags");
+
+pub fn pause_flags(storage: &dyn Storage) -> StdResult<PauseFlags> {
+    Ok(PAUSE_FLAGS.may_load(storage)?.unwrap_or_default())
+}
+
+// the address the current owner has offered to hand the contract over to
+pub const OWNERSHIP_PROPOSAL: Item<OwnershipProposal> = Item::new("ownership_proposal");
+
+// total number of tokens ever minted, including burned ones, so token ids are never reused
+pub const MINTED: Item<u64> = Item::new("minted");
+
+pub fn minted_count(storage: &dyn Storage) -> StdResult<u64> {
+    match MINTED.may_load(storage)? {
+        Some(minted) => Ok(minted),
+        // nothing was burned before this counter existed
+        None => Cw721Contract::<InternExtension, Empty>::default().token_count(storage),
+    }
+}
+
+// set once a new deployment has been seeded through `ImportTokens`, on its first mint, and on
+// migrating a deployment that already has tokens
+pub const IMPORT_SEALED: Item<bool> = Item::new("import_sealed");
+
+pub fn import_sealed(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(IMPORT_SEALED.may_load(storage)?.unwrap_or(false))
+}
+
+// storage key of the last token the index backfill got to, only set while it is pending
+pub const INDEX_BACKFILL: Item<String> = Item::new("index_backfill");
+
+// number of tokens minted by each wallet under a given allowlist nonce
+pub const ALLOWLIST_NONCES: Map<(&Addr, U64Key), u32> = Map::new("allowlist_nonces");
+
+// number of tokens minted by each wallet against its merkle allowlist quota
+pub const ALLOWLIST_CLAIMS: Map<&Addr, u32> = Map::new("allowlist_claims");
+
+// number of tokens minted during each mint phase, keyed by the phase's start in nanoseconds
+pub const PHASE_MINTED: Map<U64Key, u64> = Map::new("phase_minted");
+
+// number of tokens minted by each wallet during each mint phase
+pub const PHASE_WALLET_MINTED: Map<(U64Key, &Addr), u32> = Map::new("phase_wallet_minted");
+
+// contracts other than the staking contract that may update traits, and which ones
+pub const TRAIT_UPDATERS: Map<&Addr, Vec<TraitKind>> = Map::new("trait_updaters");
+
+// addresses that can receive transfers beyond the wallet limit, besides the staking contract
+pub const WALLET_LIMIT_EXEMPTIONS: Map<&Addr, Empty> = Map::new("wallet_limit_exemptions");
+
+// number of tokens held by each address, kept in step with `tokens()` on every mint, transfer,
+// send and burn so wallet limits don't need to count the owner index
+pub const OWNER_TOKEN_COUNTS: Map<&Addr, u64> = Map::new("owner_token_counts");
+
+pub fn owner_token_count(storage: &dyn Storage, owner: &Addr) -> StdResult<u64> {
+    Ok(OWNER_TOKEN_COUNTS.may_load(storage, owner)?.unwrap_or(0))
+}
+
+pub fn increment_owner_tokens(storage: &mut dyn Storage, owner: &Addr) -> StdResult<()> {
+    let count = owner_token_count(storage, owner)?;
+    OWNER_TOKEN_COUNTS.save(storage, owner, &(count + 1))
+}
+
+pub fn decrement_owner_tokens(storage: &mut dyn Storage, owner: &Addr) -> StdResult<()> {
+    match owner_token_count(storage, owner)? {
+        0 | 1 => OWNER_TOKEN_COUNTS.remove(storage, owner),
+        count => OWNER_TOKEN_COUNTS.save(storage, owner, &(count - 1))?,
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::convert::TryInto;
+
+use cosmwasm_std::{Addr, Env};
+use internnft::nft::{InternExtension, TraitRanges};
+use sha2::{Digest, Sha256};
+
+/// Derives the entropy used to roll the starting traits of a newly minted token.
+///
+/// This is predictable to anyone who knows the block in which the mint will land, so it
+/// only needs to be good enough to make every intern look different.
+pub fn mint_seed(env: &Env, sender: &Addr, token_id: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(env.block.chain_id.as_bytes());
+    hasher.update(env.block.height.to_be_bytes());
+    hasher.update(env.block.time.nanos().to_be_bytes());
+    hasher.update(sender.as_bytes());
+    hasher.update(token_id.as_bytes());
+    hasher.finalize().into()
+}
+
+pub fn roll_traits(ranges: &TraitRanges, seed: &[u8; 32]) -> InternExtension {
+    let word = |i: usize| u64::from_be_bytes(seed[i * 8..(i + 1) * 8].try_into().unwrap());
+    InternExtension {
+        experience: ranges.experience.pick(word(0)),
+        gold: ranges.gold.pick(word(1)),
+        stamina: ranges.stamina.pick(word(2)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::mock_env;
+    use internnft::nft::TraitRange;
+
+    fn ranges() -> TraitRanges {
+        TraitRanges {
+            experience: TraitRange { min: 0, max: 10 },
+            gold: TraitRange { min: 50, max: 150 },
+            stamina: TraitRange { min: 80, max: 120 },
+        }
+    }
+
+    #[test]
+    fn seed_depends_on_inputs() {
+        let env = mock_env();
+        let sender = Addr::unchecked("addr1");
+        let seed = mint_seed(&env, &sender, "intern #1");
+
+        // deterministic for the same inputs
+        assert_eq!(seed, mint_seed(&env, &sender, "intern #1"));
+
+        // but changes with the token, sender and block
+        assert_ne!(seed, mint_seed(&env, &sender, "intern #2"));
+        assert_ne!(
+            seed,
+            mint_seed(&env, &Addr::unchecked("addr2"), "intern #1")
+        );
+        let mut next_block = mock_env();
+        next_block.block.height += 1;
+        assert_ne!(seed, mint_seed(&next_block, &sender, "intern #1"));
+    }
+
+    #[test]
+    fn rolled_traits_stay_in_range() {
+        let env = mock_env();
+        let sender = Addr::unchecked("addr1");
+        let ranges = ranges();
+
+        for i in 0..100 {
+            let seed = mint_seed(&env, &sender, &format!("intern #{}", i));
+            let traits = roll_traits(&ranges, &seed);
+            assert!(traits.experience <= 10);
+            assert!((50..=150).contains(&traits.gold));
+            assert!((80..=120).contains(&traits.stamina));
+        }
+    }
+}
@@ -2,25 +2,34 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     from_binary, to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QueryRequest,
-    Response, StdResult, WasmMsg, WasmQuery,
+    Response, StdError, StdResult, Storage, WasmMsg, WasmQuery,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw721::{Cw721ExecuteMsg, Cw721ReceiveMsg};
-use internnft::nft::ExecuteMsg::UpdateTrait;
-use internnft::nft::InternTokenInfo;
+use internnft::migrations::{migration_batch_size, parse_version, run_migrations, Migration};
+use internnft::nft::ExecuteMsg::AdjustTraits;
+use internnft::nft::InternNftInfoResponse;
 use internnft::nft::QueryMsg::InternNftInfo;
 use internnft::staking::ContractQuery::GetRandomness;
 use internnft::staking::{
-    Config, Cw721HookMsg, ExecuteMsg, GetRandomResponse, InstantiateMsg, QueryMsg, StakingInfo,
+    Config, Cw721HookMsg, ExecuteMsg, GetRandomResponse, InstantiateMsg, MigrateMsg, QueryMsg,
+    StakingInfo,
 };
+use internnft::token_id::TokenId;
+use std::convert::TryFrom;
 
 use crate::error::ContractError;
-use crate::state::{get_staking_info, CONFIG, STAKING_INFO};
+use crate::state::{
+    continue_rekey_staking_info, get_staking_info, rekey_staking_info, CONFIG, REKEY_CURSOR,
+    STAKING_INFO,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:internnft-staking-contract";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub const MIGRATIONS: &[Migration] = &[("0.2.0", "rekey_staking_info", rekey_staking_info)];
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -73,6 +82,7 @@ pub fn execute(
             exp_constant,
         ),
         ExecuteMsg::WithdrawNft { token_id } => withdraw_nft(deps, env, info, token_id),
+        ExecuteMsg::ResumeMigration { limit } => resume_migration(deps, info, limit),
     }
 }
 
@@ -131,18 +141,21 @@ pub fn stake(
     staking_type: String,
     msg: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
+    check_no_pending_migration(deps.storage)?;
     if staking_type != "gold" && staking_type != "exp" {
         return Err(ContractError::InvalidStakingType {});
     }
 
     let config: Config = CONFIG.load(deps.storage)?;
+    let token_id: TokenId = msg.token_id.parse()?;
 
     //if this returns an error, the token does not exist and we exit
-    let token_info: InternTokenInfo =
+    let token_info: InternNftInfoResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: config.nft_contract_addr.to_string(),
             msg: to_binary(&InternNftInfo {
-                token_id: msg.token_id.clone(),
+                token_id,
+                include_staking: false,
             })?,
         }))?;
 
@@ -150,13 +163,13 @@ pub fn stake(
         return Err(ContractError::Unauthorized {});
     }
 
-    let staking_info: StakingInfo = match STAKING_INFO.has(deps.storage, msg.token_id.clone()) {
-        true => get_staking_info(&deps, msg.token_id.clone()).unwrap(),
+    let staking_info: StakingInfo = match STAKING_INFO.has(deps.storage, token_id.to_string()) {
+        true => get_staking_info(&deps, token_id.to_string()).unwrap(),
         false => StakingInfo {
             staked: false,
             last_action_block_time: 0,
             current_stamina: token_info.extension.stamina,
-            token_id: msg.token_id.clone(),
+            token_id,
             owner: sender,
             staking_type: "".to_string(),
         },
@@ -173,22 +186,19 @@ pub fn stake(
     new_staking_info.staking_type = staking_type.clone();
 
     //if the current stamina isn't the same as the max stamina in the NFT, then update the stamina
-    if staking_info.current_stamina != token_info.extension.stamina {
-        let stamina_to_add =
-            (env.block.height - staking_info.last_action_block_time) * config.stamina_constant;
-        new_staking_info.current_stamina =
-            match token_info.extension.stamina > staking_info.current_stamina + stamina_to_add {
-                true => staking_info.current_stamina + stamina_to_add,
-                false => token_info.extension.stamina,
-            };
-    }
-
-    STAKING_INFO.save(deps.storage, msg.token_id.clone(), &new_staking_info)?;
+    new_staking_info.current_stamina = recovered_stamina(
+        &config,
+        &staking_info,
+        env.block.height,
+        token_info.extension.stamina,
+    );
+
+    STAKING_INFO.save(deps.storage, token_id.to_string(), &new_staking_info)?;
     //once stamina is updated, finish
 
     Ok(Response::new()
         .add_attribute("action", "stake")
-        .add_attribute("token_id", msg.token_id)
+        .add_attribute("token_id", token_id.to_string())
         .add_attribute("staking_type", staking_type))
 }
 
@@ -197,17 +207,19 @@ pub fn withdraw_nft(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    token_id: String,
+    token_id: TokenId,
 ) -> Result<Response, ContractError> {
+    check_no_pending_migration(deps.storage)?;
     //check ownership and staking status of the NFT and return if it matches
     let config: Config = CONFIG.load(deps.storage)?;
 
     //if this returns an error, the token does not exist and we exit
-    let token_info: InternTokenInfo =
+    let token_info: InternNftInfoResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: config.nft_contract_addr.to_string(),
             msg: to_binary(&InternNftInfo {
-                token_id: token_id.clone(),
+                token_id,
+                include_staking: false,
             })?,
         }))?;
 
@@ -215,25 +227,18 @@ pub fn withdraw_nft(
         return Err(ContractError::Unauthorized {});
     }
 
-    let staking_info: StakingInfo = match STAKING_INFO.has(deps.storage, token_id.clone()) {
-        true => Ok(get_staking_info(&deps, token_id.clone()).unwrap()),
+    let staking_info: StakingInfo = match STAKING_INFO.has(deps.storage, token_id.to_string()) {
+        true => Ok(get_staking_info(&deps, token_id.to_string()).unwrap()),
         false => Err(ContractError::NoStakedToken {}),
     }?;
 
     let mut new_staking_info: StakingInfo = staking_info.clone();
-    let mut new_token_info: InternTokenInfo = token_info.clone();
 
     //update gold or experience
     //1. calculate stamina lost
     //1a. stamina_lost = blocks_elapsed * decay_rate (assuming linear decay)
 
-    let stamina_lost = match (env.block.height - staking_info.last_action_block_time)
-        * config.stamina_constant
-        > staking_info.current_stamina
-    {
-        true => staking_info.current_stamina,
-        false => (env.block.height - staking_info.last_action_block_time) * config.stamina_constant,
-    };
+    let stamina_lost = stamina_lost(&config, &staking_info, env.block.height);
 
     //updating stamina, exp, gold at the end
     new_staking_info.current_stamina = match staking_info.current_stamina < stamina_lost {
@@ -300,19 +305,16 @@ pub fn withdraw_nft(
     new_staking_info.staked = false;
     new_staking_info.last_action_block_time = env.block.height;
 
-    STAKING_INFO.save(deps.storage, token_id.clone(), &new_staking_info)?;
-
-    //updating the token information
-    new_token_info.extension.experience = token_info.extension.experience + added_exp;
-    new_token_info.extension.gold = token_info.extension.gold + added_gold;
+    STAKING_INFO.save(deps.storage, token_id.to_string(), &new_staking_info)?;
 
+    //updating the token information; the nft contract adds the rewards to the current values
     let update_message = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: config.nft_contract_addr.to_string(),
-        msg: to_binary(&UpdateTrait {
-            token_id: token_id.clone(),
-            exp: new_token_info.extension.experience,
-            gold: new_token_info.extension.gold,
-            stamina: token_info.extension.stamina,
+        msg: to_binary(&AdjustTraits {
+            token_id,
+            experience: i64::try_from(added_exp).map_err(|_| ContractError::RewardOverflow {})?,
+            gold: i64::try_from(added_gold).map_err(|_| ContractError::RewardOverflow {})?,
+            stamina: 0,
         })?,
         funds: vec![],
     });
@@ -321,7 +323,7 @@ pub fn withdraw_nft(
         contract_addr: config.nft_contract_addr.to_string(),
         msg: to_binary(&Cw721ExecuteMsg::TransferNft {
             recipient: String::from(staking_info.owner),
-            token_id: token_id.clone(),
+            token_id: token_id.to_string(),
         })?,
         funds: vec![],
     });
@@ -331,7 +333,7 @@ pub fn withdraw_nft(
     Ok(Response::new()
         .add_messages(msgs)
         .add_attribute("action", "unstake")
-        .add_attribute("token_id", token_id)
+        .add_attribute("token_id", token_id.to_string())
         .add_attribute("staking_type", staking_info.staking_type)
         .add_attribute("gold_added", added_gold.to_string())
         .add_attribute("exp_added", added_exp.to_string())
@@ -339,11 +341,57 @@ pub fn withdraw_nft(
         .add_attribute("new_stamina", new_staking_info.current_stamina.to_string()))
 }
 
+pub fn resume_migration(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let limit = migration_batch_size(limit);
+    let (processed, done) = continue_rekey_staking_info(deps.storage, limit)?;
+    Ok(Response::new()
+        .add_attribute("action", "resume_migration")
+        .add_attribute("processed", processed.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+// staking records are looked up by canonical token id, so they can't be used until the rekey
+// has moved them all
+fn check_no_pending_migration(storage: &dyn Storage) -> Result<(), ContractError> {
+    if REKEY_CURSOR.may_load(storage)?.is_some() {
+        return Err(ContractError::MigrationPending {});
+    }
+    Ok(())
+}
+
+// stamina drains by `stamina_constant` per block while a token is staked
+fn stamina_lost(config: &Config, staking_info: &StakingInfo, height: u64) -> u64 {
+    let drained = (height - staking_info.last_action_block_time) * config.stamina_constant;
+    drained.min(staking_info.current_stamina)
+}
+
+// and recovers at the same rate up to the token's stamina trait while it's not. Stamina above
+// the trait, e.g. after the trait was lowered, is clamped down to it.
+fn recovered_stamina(
+    config: &Config,
+    staking_info: &StakingInfo,
+    height: u64,
+    max_stamina: u64,
+) -> u64 {
+    let recovered = (height - staking_info.last_action_block_time) * config.stamina_constant;
+    (staking_info.current_stamina + recovered).min(max_stamina)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig {} => query_config(deps),
-        QueryMsg::GetStakingInfo { token_id } => query_staking_info(deps, token_id),
+        QueryMsg::GetStakingInfo { token_id } => query_staking_info(deps, env, token_id),
     }
 }
 
@@ -352,7 +400,60 @@ pub fn query_config(deps: Deps) -> StdResult<Binary> {
     to_binary(&config)
 }
 
-pub fn query_staking_info(deps: Deps, token_id: String) -> StdResult<Binary> {
-    let staking_info = STAKING_INFO.load(deps.storage, token_id)?;
-    to_binary(&staking_info)
+// `current_stamina` is stored as of the last stake or unstake, so it's brought up to date here
+pub fn query_staking_info(deps: Deps, env: Env, token_id: TokenId) -> StdResult<Binary> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut staking_info = match STAKING_INFO.may_load(deps.storage, token_id.to_string())? {
+        Some(staking_info) => staking_info,
+        None => return to_binary(&None::<StakingInfo>),
+    };
+    staking_info.current_stamina = if staking_info.staked {
+        staking_info.current_stamina - stamina_lost(&config, &staking_info, env.block.height)
+    } else {
+        let token_info: InternNftInfoResponse =
+            deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: config.nft_contract_addr.to_string(),
+                msg: to_binary(&InternNftInfo {
+                    token_id,
+                    include_staking: false,
+                })?,
+            }))?;
+        recovered_stamina(
+            &config,
+            &staking_info,
+            env.block.height,
+            token_info.extension.stamina,
+        )
+    };
+    to_binary(&Some(staking_info))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(StdError::generic_err("can't migrate to contract with different name").into());
+    }
+    let from_version = parse_version(&version.version)?;
+    if from_version > parse_version(CONTRACT_VERSION)? {
+        return Err(StdError::generic_err("can't migrate to an older contract version").into());
+    }
+
+    let applied = run_migrations(deps.storage, MIGRATIONS, &from_version)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(stamina_constant) = msg.stamina_constant {
+        config.stamina_constant = stamina_constant;
+    }
+    if let Some(exp_constant) = msg.exp_constant {
+        config.exp_constant = exp_constant;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from_version.to_string())
+        .add_attribute("migrations", applied.join(",")))
 }
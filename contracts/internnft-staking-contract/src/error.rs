@@ -21,4 +21,10 @@ pub enum ContractError {
 
     #[error("Invalid Staking Type")]
     InvalidStakingType {},
+
+    #[error("Reward Overflow")]
+    RewardOverflow {},
+
+    #[error("Migration in progress, run ResumeMigration until it is done")]
+    MigrationPending {},
 }
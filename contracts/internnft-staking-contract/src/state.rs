@@ -1,12 +1,28 @@
 use crate::ContractError;
-use cosmwasm_std::DepsMut;
-use cw_storage_plus::{Item, Map};
-use internnft::staking::{Config, StakingInfo};
+use cosmwasm_std::{Addr, DepsMut, Order, StdError, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+use internnft::staking::{Config, StakingInfo, STAKING_INFO_NAMESPACE};
+use internnft::token_id::TokenId;
+use serde::{Deserialize, Serialize};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
 // map containing the information for all of the tokens that have underwent staking
-pub const STAKING_INFO: Map<String, StakingInfo> = Map::new("stakers");
+pub const STAKING_INFO: Map<String, StakingInfo> = Map::new(STAKING_INFO_NAMESPACE);
+
+/// `StakingInfo` as saved by 0.1, which kept token ids as received. Those were only checked with
+/// `str::parse::<u64>`, so ids like "01" or "+1" may be stored next to the canonical "1".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LegacyStakingInfo {
+    pub staked: bool,
+    pub last_action_block_time: u64,
+    pub current_stamina: u64,
+    pub token_id: String,
+    pub owner: Addr,
+    pub staking_type: String,
+}
+
+pub const LEGACY_STAKING_INFO: Map<String, LegacyStakingInfo> = Map::new(STAKING_INFO_NAMESPACE);
 
 pub fn get_staking_info(deps: &DepsMut, token_id: String) -> Result<StakingInfo, ContractError> {
     match STAKING_INFO.load(deps.storage, token_id) {
@@ -14,3 +30,71 @@ pub fn get_staking_info(deps: &DepsMut, token_id: String) -> Result<StakingInfo,
         Err(_) => Err(ContractError::NoStakedToken {}),
     }
 }
+
+// storage key of the last staking record the rekey got to, only set while it is pending
+pub const REKEY_CURSOR: Item<String> = Item::new("rekey_cursor");
+
+/// Starts moving staking info saved under non-canonical token ids to the canonical key, see
+/// `continue_rekey_staking_info`.
+pub fn rekey_staking_info(storage: &mut dyn Storage) -> StdResult<()> {
+    REKEY_CURSOR.save(storage, &String::new())
+}
+
+/// Rekeys the next `limit` staking records of a pending rekey, as they can't all fit into one
+/// transaction. When a token has records under several ids, the most recently updated one is
+/// kept. Returns how many were processed and whether it's done.
+pub fn continue_rekey_staking_info(
+    storage: &mut dyn Storage,
+    limit: usize,
+) -> StdResult<(usize, bool)> {
+    let mut cursor = match REKEY_CURSOR.may_load(storage)? {
+        Some(cursor) => cursor,
+        None => return Ok((0, true)),
+    };
+    let mut batch: Vec<Vec<u8>> = LEGACY_STAKING_INFO
+        .keys(
+            storage,
+            Some(Bound::exclusive(cursor.clone())),
+            None,
+            Order::Ascending,
+        )
+        .take(limit + 1)
+        .collect();
+    let done = batch.len() <= limit;
+    batch.truncate(limit);
+
+    let processed = batch.len();
+    for key in batch {
+        cursor = String::from_utf8(key).map_err(StdError::invalid_utf8)?;
+        // an earlier record of the same batch may have been merged into this one, so it is
+        // read only now
+        let info = LEGACY_STAKING_INFO.load(storage, cursor.clone())?;
+        let token_id = TokenId::new(cursor.parse::<u64>().map_err(|_| {
+            StdError::generic_err(format!("unexpected staking info key: {}", cursor))
+        })?);
+        if cursor != token_id.to_string() {
+            LEGACY_STAKING_INFO.remove(storage, cursor.clone());
+            match LEGACY_STAKING_INFO.may_load(storage, token_id.to_string())? {
+                Some(current) if current.last_action_block_time >= info.last_action_block_time => {
+                    continue
+                }
+                _ => {}
+            }
+        }
+        let info = StakingInfo {
+            staked: info.staked,
+            last_action_block_time: info.last_action_block_time,
+            current_stamina: info.current_stamina,
+            token_id,
+            owner: info.owner,
+            staking_type: info.staking_type,
+        };
+        STAKING_INFO.save(storage, token_id.to_string(), &info)?;
+    }
+    if done {
+        REKEY_CURSOR.remove(storage);
+    } else {
+        REKEY_CURSOR.save(storage, &cursor)?;
+    }
+    Ok((processed, done))
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use internnft::staking::GetRandomResponse;
 
-use internnft::nft::{InternExtension, InternTokenInfo};
+use internnft::nft::{InternExtension, InternNftInfoResponse};
 use terra_cosmwasm::TerraQueryWrapper;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -64,7 +64,7 @@ impl WasmMockQuerier {
                     })))
                 }
                 QueryMsg::InternNftInfo { token_id } => {
-                    SystemResult::Ok(ContractResult::from(to_binary(&InternTokenInfo {
+                    SystemResult::Ok(ContractResult::from(to_binary(&InternNftInfoResponse {
                         owner: Addr::unchecked(String::from("addr000") + token_id.as_str()),
                         approvals: vec![],
                         name: token_id,
@@ -75,6 +75,9 @@ impl WasmMockQuerier {
                             gold: 0,
                             stamina: 100,
                         },
+                        token_uri: None,
+                        external_url: None,
+                        background_color: None,
                     })))
                 }
             },
@@ -1,10 +1,18 @@
 use cosmwasm_std::testing::{mock_env, mock_info};
-use cosmwasm_std::{to_binary, Addr, CosmosMsg, Response, Timestamp, WasmMsg};
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Order, Response, StdError, Timestamp, WasmMsg};
+use cw2::{get_contract_version, set_contract_version};
 use cw721::{Cw721ExecuteMsg, Cw721ReceiveMsg};
-use internnft::nft::ExecuteMsg::UpdateTrait;
-use internnft::staking::{Config, Cw721HookMsg, InstantiateMsg, StakingInfo};
-
-use crate::contract::{instantiate, query_config, query_staking_info, stake, withdraw_nft};
+use internnft::nft::ExecuteMsg::AdjustTraits;
+use internnft::staking::{Config, Cw721HookMsg, InstantiateMsg, MigrateMsg, StakingInfo};
+use internnft::token_id::TokenId;
+
+use crate::contract::{
+    instantiate, migrate, query_config, query_staking_info, resume_migration, stake, withdraw_nft,
+};
+use crate::state::{
+    continue_rekey_staking_info, rekey_staking_info, LegacyStakingInfo, LEGACY_STAKING_INFO,
+    REKEY_CURSOR, STAKING_INFO,
+};
 use crate::testing::mock_querier::mock_dependencies;
 use crate::ContractError;
 
@@ -107,13 +115,14 @@ fn test_gold_staking() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender,
         staking_type: "gold".to_string(),
     })
@@ -165,13 +174,14 @@ fn test_exp_staking() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender,
         staking_type: "exp".to_string(),
     })
@@ -236,13 +246,14 @@ fn test_staking_token_twice() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender,
         staking_type: "exp".to_string(),
     })
@@ -340,13 +351,14 @@ fn test_stake_unstake_gold_stamina_not_depleted() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender.clone(),
         staking_type: "gold".to_string(),
     })
@@ -357,7 +369,7 @@ fn test_stake_unstake_gold_stamina_not_depleted() {
     let staked_blocks = 10;
     env.block.height += staked_blocks;
 
-    let unstake_res = withdraw_nft(deps.as_mut(), env, info, "0".to_string()).unwrap();
+    let unstake_res = withdraw_nft(deps.as_mut(), env, info, TokenId::new(0)).unwrap();
 
     let mut added_gold = 0;
     for reward in gold_rewards.iter().take(staked_blocks as usize) {
@@ -367,11 +379,11 @@ fn test_stake_unstake_gold_stamina_not_depleted() {
     let msgs = vec![
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "internnft0000".to_string(),
-            msg: to_binary(&UpdateTrait {
-                token_id: "0".to_string(),
-                exp: 0,
-                gold: added_gold,
-                stamina: 100,
+            msg: to_binary(&AdjustTraits {
+                token_id: TokenId::new(0),
+                experience: 0,
+                gold: added_gold as i64,
+                stamina: 0,
             })
             .unwrap(),
             funds: vec![],
@@ -444,13 +456,14 @@ fn test_stake_unstake_exp_stamina_not_depleted() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender.clone(),
         staking_type: "exp".to_string(),
     })
@@ -461,16 +474,16 @@ fn test_stake_unstake_exp_stamina_not_depleted() {
     let staked_blocks = 10;
     env.block.height += staked_blocks;
 
-    let unstake_res = withdraw_nft(deps.as_mut(), env, info, "0".to_string()).unwrap();
+    let unstake_res = withdraw_nft(deps.as_mut(), env, info, TokenId::new(0)).unwrap();
 
     let msgs = vec![
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "internnft0000".to_string(),
-            msg: to_binary(&UpdateTrait {
-                token_id: "0".to_string(),
-                exp: staked_blocks,
+            msg: to_binary(&AdjustTraits {
+                token_id: TokenId::new(0),
+                experience: staked_blocks as i64,
                 gold: 0,
-                stamina: 100,
+                stamina: 0,
             })
             .unwrap(),
             funds: vec![],
@@ -516,7 +529,7 @@ fn test_unstake_without_stake() {
     let _instantiate_res =
         instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
 
-    let unstake_res = withdraw_nft(deps.as_mut(), env, info, "0".to_string());
+    let unstake_res = withdraw_nft(deps.as_mut(), env, info, TokenId::new(0));
 
     match unstake_res {
         Err(ContractError::NoStakedToken {}) => (),
@@ -568,13 +581,14 @@ fn test_unstake_unowned() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender.clone(),
         staking_type: "exp".to_string(),
     })
@@ -585,7 +599,7 @@ fn test_unstake_unowned() {
     let staked_blocks = 10;
     env.block.height += staked_blocks;
 
-    let unstake_res = withdraw_nft(deps.as_mut(), env, info, "1".to_string());
+    let unstake_res = withdraw_nft(deps.as_mut(), env, info, TokenId::new(1));
 
     match unstake_res {
         Err(ContractError::Unauthorized {}) => (),
@@ -637,13 +651,14 @@ fn test_stake_unstake_gold_stamina_depleted() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender.clone(),
         staking_type: "gold".to_string(),
     })
@@ -655,18 +670,18 @@ fn test_stake_unstake_gold_stamina_depleted() {
     env.block.height += staked_blocks;
 
     let unstake_res =
-        withdraw_nft(deps.as_mut(), env.clone(), info.clone(), "0".to_string()).unwrap();
+        withdraw_nft(deps.as_mut(), env.clone(), info.clone(), TokenId::new(0)).unwrap();
 
     let added_gold = 144;
 
     let msgs = vec![
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "internnft0000".to_string(),
-            msg: to_binary(&UpdateTrait {
-                token_id: "0".to_string(),
-                exp: 0,
-                gold: added_gold,
-                stamina: 100,
+            msg: to_binary(&AdjustTraits {
+                token_id: TokenId::new(0),
+                experience: 0,
+                gold: added_gold as i64,
+                stamina: 0,
             })
             .unwrap(),
             funds: vec![],
@@ -694,13 +709,14 @@ fn test_stake_unstake_gold_stamina_depleted() {
 
     assert_eq!(unstake_res, unstake_test_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: false,
         last_action_block_time: env.block.height,
         current_stamina: 0,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender,
         staking_type: "gold".to_string(),
     })
@@ -753,13 +769,14 @@ fn test_stake_unstake_exp_stamina_depleted() {
 
     assert_eq!(staking_res, test_staking_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: true,
         last_action_block_time: env.block.height,
         current_stamina: 100,
-        token_id: "0".to_string(),
+        token_id: TokenId::new(0),
         owner: info.sender.clone(),
         staking_type: "exp".to_string(),
     })
@@ -771,18 +788,18 @@ fn test_stake_unstake_exp_stamina_depleted() {
     env.block.height += staked_blocks;
 
     let unstake_res =
-        withdraw_nft(deps.as_mut(), env.clone(), info.clone(), "0".to_string()).unwrap();
+        withdraw_nft(deps.as_mut(), env.clone(), info.clone(), TokenId::new(0)).unwrap();
 
     let added_exp = 100;
 
     let msgs = vec![
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "internnft0000".to_string(),
-            msg: to_binary(&UpdateTrait {
-                token_id: "0".to_string(),
-                exp: added_exp,
+            msg: to_binary(&AdjustTraits {
+                token_id: TokenId::new(0),
+                experience: added_exp as i64,
                 gold: 0,
-                stamina: 100,
+                stamina: 0,
             })
             .unwrap(),
             funds: vec![],
@@ -810,13 +827,343 @@ fn test_stake_unstake_exp_stamina_depleted() {
 
     assert_eq!(unstake_res, unstake_test_res);
 
-    let query_staking_res = query_staking_info(deps.as_ref(), "0".to_string()).unwrap();
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
 
     let test_staking_res = to_binary(&StakingInfo {
         staked: false,
         last_action_block_time: env.block.height,
         current_stamina: 0,
+        token_id: TokenId::new(0),
+        owner: info.sender,
+        staking_type: "exp".to_string(),
+    })
+    .unwrap();
+    assert_eq!(query_staking_res, test_staking_res);
+
+    // tokens that were never staked have no record
+    let query_staking_res = query_staking_info(deps.as_ref(), env, TokenId::new(1)).unwrap();
+    assert_eq!(query_staking_res, to_binary(&None::<StakingInfo>).unwrap());
+}
+
+fn legacy_staking_info(token_id: &str, last_action_block_time: u64) -> LegacyStakingInfo {
+    LegacyStakingInfo {
+        staked: true,
+        last_action_block_time,
+        current_stamina: 50,
+        token_id: token_id.to_string(),
+        owner: Addr::unchecked("addr0000"),
+        staking_type: "gold".to_string(),
+    }
+}
+
+#[test]
+fn test_rekey_staking_info() {
+    let mut deps = mock_dependencies(&[]);
+    for &(token_id, last_action_block_time) in [("01", 5), ("1", 10), ("+2", 7), ("3", 3)].iter() {
+        LEGACY_STAKING_INFO
+            .save(
+                deps.as_mut().storage,
+                token_id.to_string(),
+                &legacy_staking_info(token_id, last_action_block_time),
+            )
+            .unwrap();
+    }
+
+    rekey_staking_info(deps.as_mut().storage).unwrap();
+    // the records are moved in batches
+    assert_eq!(
+        continue_rekey_staking_info(deps.as_mut().storage, 2).unwrap(),
+        (2, false)
+    );
+    assert_eq!(
+        continue_rekey_staking_info(deps.as_mut().storage, 2).unwrap(),
+        (2, false)
+    );
+    assert_eq!(
+        continue_rekey_staking_info(deps.as_mut().storage, 2).unwrap(),
+        (1, true)
+    );
+    assert!(REKEY_CURSOR
+        .may_load(deps.as_ref().storage)
+        .unwrap()
+        .is_none());
+
+    let keys: Vec<Vec<u8>> = LEGACY_STAKING_INFO
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect();
+    assert_eq!(keys, vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+    // the most recent record wins
+    let info = STAKING_INFO
+        .load(deps.as_ref().storage, "1".to_string())
+        .unwrap();
+    assert_eq!(info.last_action_block_time, 10);
+    let info = STAKING_INFO
+        .load(deps.as_ref().storage, "2".to_string())
+        .unwrap();
+    assert_eq!(info.token_id, TokenId::new(2));
+    assert_eq!(info.last_action_block_time, 7);
+
+    // keys that aren't token ids at all are rejected
+    LEGACY_STAKING_INFO
+        .save(
+            deps.as_mut().storage,
+            "intern #4".to_string(),
+            &legacy_staking_info("intern #4", 1),
+        )
+        .unwrap();
+    rekey_staking_info(deps.as_mut().storage).unwrap();
+    let err = continue_rekey_staking_info(deps.as_mut().storage, 10).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unexpected staking info key: intern #4")
+    );
+}
+
+#[test]
+fn test_rekey_keeps_newer_non_canonical_record() {
+    let mut deps = mock_dependencies(&[]);
+    for &(token_id, last_action_block_time) in [("01", 10), ("1", 5)].iter() {
+        LEGACY_STAKING_INFO
+            .save(
+                deps.as_mut().storage,
+                token_id.to_string(),
+                &legacy_staking_info(token_id, last_action_block_time),
+            )
+            .unwrap();
+    }
+
+    // both records are in the same batch
+    rekey_staking_info(deps.as_mut().storage).unwrap();
+    assert_eq!(
+        continue_rekey_staking_info(deps.as_mut().storage, 10).unwrap(),
+        (2, true)
+    );
+
+    let keys: Vec<Vec<u8>> = LEGACY_STAKING_INFO
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect();
+    assert_eq!(keys, vec![b"1".to_vec()]);
+    let info = STAKING_INFO
+        .load(deps.as_ref().storage, "1".to_string())
+        .unwrap();
+    assert_eq!(info.last_action_block_time, 10);
+}
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        owner: Addr::unchecked("owner0000"),
+        nft_contract_addr: Addr::unchecked("internnft0000"),
+        terrand_addr: Addr::unchecked("terrand0000"),
+        stamina_constant: 1,
+        exp_constant: 1,
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // other contracts and newer versions can't be migrated from
+    set_contract_version(deps.as_mut().storage, "crates.io:other-contract", "0.1.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: can't migrate to contract with different name"
+    );
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:internnft-staking-contract",
+        "99.0.0",
+    )
+    .unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: can't migrate to an older contract version"
+    );
+
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:internnft-staking-contract",
+        "0.1.0",
+    )
+    .unwrap();
+    LEGACY_STAKING_INFO
+        .save(
+            deps.as_mut().storage,
+            "07".to_string(),
+            &legacy_staking_info("07", 1),
+        )
+        .unwrap();
+    let res = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            stamina_constant: Some(2),
+            exp_constant: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "migrate")
+            .add_attribute("from_version", "0.1.0")
+            .add_attribute("migrations", "rekey_staking_info")
+    );
+    assert_eq!(
+        get_contract_version(deps.as_ref().storage).unwrap().version,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    // staking waits for the records to be moved
+    let receive_msg = Cw721ReceiveMsg {
+        sender: "addr0000".to_string(),
+        token_id: "7".to_string(),
+        msg: to_binary(&Cw721HookMsg::Stake {
+            staking_type: "gold".to_string(),
+        })
+        .unwrap(),
+    };
+    let err = stake(
+        deps.as_mut(),
+        mock_env(),
+        Addr::unchecked("addr0000"),
+        "gold".to_string(),
+        receive_msg,
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::MigrationPending {}));
+    let err = withdraw_nft(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        TokenId::new(7),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::MigrationPending {}));
+
+    // which only the owner can run
+    let err = resume_migration(deps.as_mut(), mock_info("addr0000", &[]), None).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let res = resume_migration(deps.as_mut(), mock_info("owner0000", &[]), None).unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "resume_migration")
+            .add_attribute("processed", "1")
+            .add_attribute("done", "true")
+    );
+
+    let info = STAKING_INFO
+        .load(deps.as_ref().storage, "7".to_string())
+        .unwrap();
+    assert_eq!(
+        info,
+        StakingInfo {
+            staked: true,
+            last_action_block_time: 1,
+            current_stamina: 50,
+            token_id: TokenId::new(7),
+            owner: Addr::unchecked("addr0000"),
+            staking_type: "gold".to_string(),
+        }
+    );
+    let config = query_config(deps.as_ref()).unwrap();
+    assert_eq!(
+        config,
+        to_binary(&Config {
+            nft_contract_addr: Addr::unchecked("internnft0000"),
+            terrand_addr: Addr::unchecked("terrand0000"),
+            owner: Addr::unchecked("owner0000"),
+            stamina_constant: 2,
+            exp_constant: 1,
+        })
+        .unwrap()
+    );
+
+    // migrating again at the same version runs no upgrades
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+    assert_eq!(res.attributes[2].value, "");
+}
+
+#[test]
+fn test_query_live_stamina() {
+    let mut deps = mock_dependencies(&[]);
+    let mut env = mock_env();
+    let instantiate_msg = InstantiateMsg {
+        owner: Addr::unchecked("owner0000"),
+        nft_contract_addr: Addr::unchecked("internnft0000"),
+        terrand_addr: Addr::unchecked("terrand0000"),
+        stamina_constant: 2,
+        exp_constant: 1,
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    let _instantiate_res =
+        instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
+
+    let receive_msg = Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
         token_id: "0".to_string(),
+        msg: to_binary(&Cw721HookMsg::Stake {
+            staking_type: "exp".to_string(),
+        })
+        .unwrap(),
+    };
+
+    let _staking_res = stake(
+        deps.as_mut(),
+        env.clone(),
+        info.sender.clone(),
+        "exp".to_string(),
+        receive_msg,
+    )
+    .unwrap();
+    let staked_at = env.block.height;
+
+    // stamina drains while staked, though it's only saved on unstaking
+    env.block.height += 10;
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
+    let test_staking_res = to_binary(&StakingInfo {
+        staked: true,
+        last_action_block_time: staked_at,
+        current_stamina: 80,
+        token_id: TokenId::new(0),
+        owner: info.sender.clone(),
+        staking_type: "exp".to_string(),
+    })
+    .unwrap();
+    assert_eq!(query_staking_res, test_staking_res);
+
+    let _unstake_res =
+        withdraw_nft(deps.as_mut(), env.clone(), info.clone(), TokenId::new(0)).unwrap();
+    let unstaked_at = env.block.height;
+
+    // and recovers up to the token's stamina once unstaked
+    env.block.height += 5;
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
+    let test_staking_res = to_binary(&StakingInfo {
+        staked: false,
+        last_action_block_time: unstaked_at,
+        current_stamina: 90,
+        token_id: TokenId::new(0),
+        owner: info.sender.clone(),
+        staking_type: "exp".to_string(),
+    })
+    .unwrap();
+    assert_eq!(query_staking_res, test_staking_res);
+
+    env.block.height += 100;
+    let query_staking_res =
+        query_staking_info(deps.as_ref(), env.clone(), TokenId::new(0)).unwrap();
+    let test_staking_res = to_binary(&StakingInfo {
+        staked: false,
+        last_action_block_time: unstaked_at,
+        current_stamina: 100,
+        token_id: TokenId::new(0),
         owner: info.sender,
         staking_type: "exp".to_string(),
     })
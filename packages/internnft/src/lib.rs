@@ -1,2 +1,5 @@
+pub mod migrations;
 pub mod nft;
 pub mod staking;
+pub mod svg;
+pub mod token_id;
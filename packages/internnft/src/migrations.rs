@@ -0,0 +1,76 @@
+use cosmwasm_std::{StdError, StdResult, Storage};
+use semver::Version;
+
+pub type MigrationStep = fn(&mut dyn Storage) -> StdResult<()>;
+
+/// A state upgrade with the contract version that introduced it and its name. Contracts list
+/// theirs in the order they run, new steps go at the end.
+pub type Migration = (&'static str, &'static str, MigrationStep);
+
+/// Runs the steps introduced after `from`, returning their names.
+pub fn run_migrations(
+    storage: &mut dyn Storage,
+    migrations: &[Migration],
+    from: &Version,
+) -> StdResult<Vec<&'static str>> {
+    let mut applied = vec![];
+    for (version, name, step) in migrations {
+        if parse_version(version)? > *from {
+            step(storage)?;
+            applied.push(*name);
+        }
+    }
+    Ok(applied)
+}
+
+pub fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version).map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+const DEFAULT_MIGRATION_BATCH: u32 = 100;
+const MAX_MIGRATION_BATCH: u32 = 250;
+
+/// How many items a `ResumeMigration` call processes for the requested `limit`
+pub fn migration_batch_size(limit: Option<u32>) -> usize {
+    limit
+        .unwrap_or(DEFAULT_MIGRATION_BATCH)
+        .min(MAX_MIGRATION_BATCH) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    fn first(storage: &mut dyn Storage) -> StdResult<()> {
+        storage.set(b"first", b"1");
+        Ok(())
+    }
+
+    fn second(storage: &mut dyn Storage) -> StdResult<()> {
+        storage.set(b"second", b"1");
+        Ok(())
+    }
+
+    const MIGRATIONS: &[Migration] = &[("0.2.0", "first", first), ("0.3.0", "second", second)];
+
+    #[test]
+    fn only_newer_steps_run() {
+        let mut storage = MockStorage::new();
+        let applied = run_migrations(&mut storage, MIGRATIONS, &Version::new(0, 2, 0)).unwrap();
+        assert_eq!(applied, vec!["second"]);
+        assert_eq!(storage.get(b"first"), None);
+
+        let applied = run_migrations(&mut storage, MIGRATIONS, &Version::new(0, 1, 0)).unwrap();
+        assert_eq!(applied, vec!["first", "second"]);
+        assert_eq!(storage.get(b"first"), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn batch_size() {
+        assert_eq!(migration_batch_size(None), 100);
+        assert_eq!(migration_batch_size(Some(5)), 5);
+        assert_eq!(migration_batch_size(Some(1000)), 250);
+    }
+}
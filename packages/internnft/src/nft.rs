@@ -1,11 +1,21 @@
+use std::convert::TryFrom;
+use std::fmt;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Binary, Coin, StdError, StdResult};
+use cosmwasm_std::{
+    to_binary, Addr, Binary, BlockInfo, Coin, CosmosMsg, Order, StdError, StdResult, Timestamp,
+    WasmMsg,
+};
 use cw721::{Expiration, OwnerOfResponse};
 use cw721_base::msg::{ExecuteMsg as CW721ExecuteMsg, QueryMsg as CW721QueryMsg};
 use cw721_base::state::Approval;
 
+use crate::staking::StakingInfo;
+use crate::svg::{render_intern_card, svg_data_uri};
+use crate::token_id::TokenId;
+
 // ----------------- begin CW721 ^0.9.2 shim ----------------- //
 
 // adapted from: https://github.com/CosmWasm/cw-nfts/blob/5e1e72a3682f988d4504b94f2e203dd4a5a99ad9/contracts/cw721-metadata-onchain/src/lib.rs#L7-L26
@@ -49,18 +59,285 @@ pub struct Cw721AllNftInfoResponse {
     pub info: Cw721NftInfoResponse,
 }
 
+// mirrors the approval queries added in later cw721 versions
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Cw721ApprovalResponse {
+    pub approval: cw721::Approval,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Cw721ApprovalsResponse {
+    pub approvals: Vec<cw721::Approval>,
+}
+
+// cw721's TokensResponse, with the numeric ids and cursor of the token lists
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Cw721TokensResponse {
+    pub tokens: Vec<String>,
+    pub next_cursor: Option<TokenId>,
+}
+
 // ----------------- end CW721 ^0.9.2 shim----------------- //
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     /// The maximum allowed number of xyz tokens
     pub token_supply: u64,
-    /// The maximum number of tokens a particular wallet can hold
+    /// The maximum number of tokens a particular wallet can hold, through mints or transfers.
+    /// Transfers to the staking contract and to exempted addresses aren't limited.
     pub wallet_limit: u32,
     /// The price to mint a new xyz (doesn't apply to the contract owner)
     pub mint_fee: Coin,
     //the staking contract that can make changes to gold and exp
     pub staking_contract: String,
+    /// The ranges newly minted interns draw their starting traits from
+    #[serde(default)]
+    pub starting_traits: TraitRanges,
+    /// DER-encoded PKCS#1 RSA public key of the backend that signs allowlist mints
+    #[serde(default)]
+    pub allowlist_public_key: Option<Binary>,
+    /// Root of a merkle tree whose leaves are the sha256 hashes of `allowlist_leaf` for every
+    /// allowlisted wallet
+    #[serde(default)]
+    pub allowlist_merkle_root: Option<Binary>,
+    /// While set, only wallets holding an allowlist signature or merkle proof can mint
+    /// (doesn't apply to the contract owner)
+    #[serde(default)]
+    pub presale: bool,
+    /// Scheduled sale phases, ordered by time. When set, these replace `mint_fee` and
+    /// `presale`, and minting is closed outside of them (doesn't apply to the contract owner).
+    #[serde(default)]
+    pub mint_phases: Vec<MintPhase>,
+    /// Contract notified with `InternBurnedMsg` whenever an intern is burned, e.g. to refund
+    /// its gold
+    #[serde(default)]
+    pub burn_hook: Option<String>,
+}
+
+/// Operations the owner can halt, e.g. while investigating an exploit. Queries keep working.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseFlags {
+    /// `Mint` and `BatchMint`
+    #[serde(default)]
+    pub minting: bool,
+    /// cw721 transfers, sends and approvals, and `Burn`. Revoking approvals stays possible.
+    #[serde(default)]
+    pub transfers: bool,
+    /// `UpdateTrait` and `AdjustTraits`
+    #[serde(default)]
+    pub trait_updates: bool,
+}
+
+/// Collection-wide settings filled into every token's metadata
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct MetadataConfig {
+    /// Template for each token's URI. `{id}` is replaced by the numeric token id, which is
+    /// appended if the template doesn't contain it, e.g. "ipfs://<cid>/".
+    pub base_uri: Option<String>,
+    /// Template for each token's external URL, with the same `{id}` placeholder
+    pub external_url: Option<String>,
+    /// Six-digit hex color without a leading `#`, e.g. "1b1f3b"
+    pub background_color: Option<String>,
+}
+
+impl MetadataConfig {
+    pub fn validate(&self) -> StdResult<()> {
+        if let Some(color) = &self.background_color {
+            if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(StdError::generic_err(
+                    "invalid background color: expected six hex digits",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn token_uri(&self, numeric_token_id: &str) -> Option<String> {
+        self.base_uri.as_ref().map(|template| {
+            if template.contains("{id}") {
+                template.replace("{id}", numeric_token_id)
+            } else {
+                format!("{}{}", template, numeric_token_id)
+            }
+        })
+    }
+
+    pub fn external_url(&self, numeric_token_id: &str) -> Option<String> {
+        self.external_url
+            .as_ref()
+            .map(|template| template.replace("{id}", numeric_token_id))
+    }
+}
+
+impl Config {
+    /// Returns the phase active at the given block along with its position in `mint_phases`
+    pub fn active_phase(&self, block: &BlockInfo) -> Option<(usize, &MintPhase)> {
+        self.mint_phases
+            .iter()
+            .enumerate()
+            .find(|(_, phase)| phase.start <= block.time && block.time < phase.end)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MintPhaseKind {
+    Closed,
+    Allowlist,
+    Public,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintPhase {
+    pub kind: MintPhaseKind,
+    /// Inclusive start of the phase. Mints counted against the caps stay with the phase across
+    /// config updates as long as its start is unchanged.
+    pub start: Timestamp,
+    /// Exclusive end of the phase
+    pub end: Timestamp,
+    pub price: Coin,
+    /// The maximum number of tokens a wallet can mint during this phase
+    pub wallet_cap: u32,
+    /// The maximum number of tokens that can be minted during this phase
+    pub supply_cap: u64,
+}
+
+/// Checks that every phase is well-formed and that phases don't overlap.
+pub fn validate_mint_phases(phases: &[MintPhase]) -> StdResult<()> {
+    for (i, phase) in phases.iter().enumerate() {
+        if phase.start >= phase.end {
+            return Err(StdError::generic_err(format!(
+                "invalid mint phase {}: start must be before end",
+                i
+            )));
+        }
+        if i > 0 && phase.start < phases[i - 1].end {
+            return Err(StdError::generic_err(format!(
+                "invalid mint phase {}: overlaps the previous phase",
+                i
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A backend signature allowing `sender` to mint up to `max_quantity` tokens under `nonce`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowlistSignature {
+    pub nonce: u64,
+    pub max_quantity: u32,
+    /// PKCS#1 v1.5 signature over the SHA-256 hash of `allowlist_message`
+    pub signature: Binary,
+}
+
+/// The message the allowlist backend signs for a given wallet. The chain id and contract address
+/// keep a signature from being replayed on other deployments sharing the backend key.
+pub fn allowlist_message(
+    chain_id: &str,
+    contract: &str,
+    sender: &str,
+    nonce: u64,
+    max_quantity: u32,
+) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        chain_id, contract, sender, nonce, max_quantity
+    )
+}
+
+/// Proof that the sender may mint up to `quota` tokens under the configured merkle root
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowlistProof {
+    pub quota: u32,
+    /// Sibling hashes from the leaf up to the root. Each pair is hashed in sorted order.
+    pub proof: Vec<Binary>,
+}
+
+/// The merkle leaf preimage for an allowlisted wallet
+pub fn allowlist_leaf(address: &str, quota: u32) -> String {
+    format!("{}:{}", address, quota)
+}
+
+/// An inclusive range of values a trait can take
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Copy)]
+pub struct TraitRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl TraitRange {
+    pub fn fixed(value: u64) -> Self {
+        TraitRange {
+            min: value,
+            max: value,
+        }
+    }
+
+    /// Maps a random value onto the range
+    pub fn pick(&self, random: u64) -> u64 {
+        match (self.max - self.min).checked_add(1) {
+            Some(width) => self.min + random % width,
+            None => random,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Copy)]
+pub struct TraitRanges {
+    pub experience: TraitRange,
+    pub gold: TraitRange,
+    pub stamina: TraitRange,
+}
+
+impl Default for TraitRanges {
+    fn default() -> Self {
+        TraitRanges {
+            experience: TraitRange::fixed(0),
+            gold: TraitRange::fixed(0),
+            stamina: TraitRange::fixed(100),
+        }
+    }
+}
+
+impl TraitRanges {
+    pub fn validate(&self) -> StdResult<()> {
+        for (name, range) in [
+            ("experience", &self.experience),
+            ("gold", &self.gold),
+            ("stamina", &self.stamina),
+        ]
+        .iter()
+        {
+            if range.min > range.max {
+                return Err(StdError::generic_err(format!(
+                    "invalid {} range: min is greater than max",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single intern trait, used to scope what a trait updater contract may change
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitKind {
+    Experience,
+    Gold,
+    Stamina,
+}
+
+impl TraitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraitKind::Experience => "experience",
+            TraitKind::Gold => "gold",
+            TraitKind::Stamina => "stamina",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Copy)]
@@ -90,6 +367,48 @@ impl InternExtension {
             },
         ]
     }
+
+    /// Level 1 at no experience, going up at 100, 400, 900, ... experience
+    pub fn level(&self) -> u64 {
+        isqrt(self.experience / 100) + 1
+    }
+}
+
+/// Trait bounds for `SearchTokens`, unset bounds match every token
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct TraitFilter {
+    pub min_experience: Option<u64>,
+    pub max_experience: Option<u64>,
+    pub min_gold: Option<u64>,
+    pub min_stamina: Option<u64>,
+}
+
+impl TraitFilter {
+    // `Option::is_none_or` needs a newer toolchain than the contracts are built with
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn matches(&self, traits: &InternExtension) -> bool {
+        self.min_experience
+            .map_or(true, |min| traits.experience >= min)
+            && self
+                .max_experience
+                .map_or(true, |max| traits.experience <= max)
+            && self.min_gold.map_or(true, |min| traits.gold >= min)
+            && self.min_stamina.map_or(true, |min| traits.stamina >= min)
+    }
+}
+
+// integer square root, contracts can't use floats
+fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = n / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -103,54 +422,143 @@ pub struct InternTokenInfo {
 }
 
 impl InternTokenInfo {
+    /// The token as queries return it, with the collection-wide metadata settings filled in
+    pub fn into_response(
+        self,
+        numeric_token_id: &str,
+        metadata: &MetadataConfig,
+    ) -> InternNftInfoResponse {
+        InternNftInfoResponse {
+            token_uri: metadata.token_uri(numeric_token_id),
+            external_url: metadata.external_url(numeric_token_id),
+            background_color: metadata.background_color.clone(),
+            owner: self.owner,
+            approvals: self.approvals,
+            name: self.name,
+            description: self.description,
+            image: self.image,
+            extension: self.extension,
+        }
+    }
+}
+
+/// A token with the settings from `MetadataConfig`, which are never stored per token
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InternNftInfoResponse {
+    pub owner: Addr,
+    pub approvals: Vec<Approval>,
+    pub name: String,
+    pub description: String,
+    pub image: Option<String>,
+    pub extension: InternExtension,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+}
+
+impl InternNftInfoResponse {
+    /// The rendered stat card as a base64 SVG data URI
+    pub fn image_data(&self) -> String {
+        svg_data_uri(&render_intern_card(&self.name, &self.extension))
+    }
+
     pub fn as_cw721_nft_info(&self) -> Cw721NftInfoResponse {
+        let image_data = self.image_data();
         Cw721NftInfoResponse {
-            token_uri: None,
+            token_uri: self.token_uri.clone(),
             extension: Cw721Metadata {
                 name: Some(self.name.clone()),
-                // TODO: Put something for image.
-                image: None,
+                // fall back to the rendered card for wallets that only read `image`
+                image: Some(self.image.clone().unwrap_or_else(|| image_data.clone())),
                 description: Some(self.description.clone()),
                 attributes: Some(self.extension.as_traits()),
-                image_data: None,
-                external_url: None,
+                image_data: Some(image_data),
+                external_url: self.external_url.clone(),
                 animation_url: None,
-                background_color: None,
+                background_color: self.background_color.clone(),
                 youtube_url: None,
             },
         }
     }
 }
 
-pub fn full_token_id(numeric_token_id: String) -> StdResult<String> {
-    // make sure the string is an integer
-    numeric_token_id
-        .parse::<u64>()
-        .map_err(|_| StdError::generic_err("expected numeric token identifier"))?;
-    Ok(format!("intern #{}", numeric_token_id))
+/// Token id prefix used by collections instantiated before it was configurable
+pub const DEFAULT_TOKEN_PREFIX: &str = "intern #";
+
+pub fn full_token_id(prefix: &str, numeric_token_id: String) -> StdResult<String> {
+    Ok(numeric_token_id.parse::<TokenId>()?.full(prefix))
+}
+
+pub fn numeric_token_id(prefix: &str, full_token_id: String) -> StdResult<String> {
+    Ok(TokenId::from_full(prefix, &full_token_id)?.to_string())
+}
+
+/// Sent to `Config.burn_hook` when an intern is burned
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InternBurnedMsg {
+    pub token_id: TokenId,
+    /// The owner of the token at the time it was burned
+    pub owner: String,
+    pub experience: u64,
+    pub gold: u64,
 }
 
-pub fn numeric_token_id(full_token_id: String) -> StdResult<String> {
-    if !full_token_id.starts_with("intern #") {
-        return Err(StdError::generic_err(
-            "expected full token identifier, like 'intern #123'",
-        ));
+impl InternBurnedMsg {
+    /// serializes the message, wrapped as `{"intern_burned": {...}}`
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = BurnHookExecuteMsg::InternBurned(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg: self.into_binary()?,
+            funds: vec![],
+        };
+        Ok(execute.into())
     }
-    Ok(full_token_id.trim_start_matches("intern #").to_string())
+}
+
+/// The burn hook contract should include this variant in its ExecuteMsg enum
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+enum BurnHookExecuteMsg {
+    InternBurned(InternBurnedMsg),
 }
 
 /// This overrides the ExecuteMsg enum defined in cw721-base
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
+    /// Collection name and symbol reported by `ContractInfo`
+    pub name: String,
+    pub symbol: String,
+    /// Prepended to the numeric id to form each token's stored id and name, e.g. "intern #"
+    pub token_prefix: String,
     pub config: Config,
+    #[serde(default)]
+    pub metadata: MetadataConfig,
 }
 
 /// This overrides the ExecuteMsg enum defined in cw721-base
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Mint {},
+    /// Mint a new token to the sender. During the presale either an allowlist signature or a
+    /// merkle proof is required.
+    Mint {
+        signature: Option<AllowlistSignature>,
+        proof: Option<AllowlistProof>,
+    },
+    /// Owner-only: mint the given number of tokens to each recipient, free of charge.
+    BatchMint {
+        recipients: Vec<(String, u32)>,
+    },
     /// Update token minting and supply configuration.
     UpdateConfig {
         config: Config,
@@ -159,30 +567,99 @@ pub enum ExecuteMsg {
     Withdraw {
         amount: Vec<Coin>,
     },
+    /// Offer ownership of the contract to a new address, which has to accept it before the
+    /// expiration. Replaces any pending proposal.
+    ProposeNewOwner {
+        owner: String,
+        expires: Option<Expiration>,
+    },
+    /// Accept a pending ownership proposal made to the sender.
+    AcceptOwnership {},
+    /// Withdraw the pending ownership proposal.
+    CancelOwnershipProposal {},
     UpdateTrait {
-        token_id: String,
+        token_id: TokenId,
         exp: u64,
         gold: u64,
         stamina: u64,
     },
+    /// Add signed deltas to a token's traits, failing if any would over- or underflow.
+    /// Response data: the resulting InternExtension
+    AdjustTraits {
+        token_id: TokenId,
+        #[serde(default)]
+        experience: i64,
+        #[serde(default)]
+        gold: i64,
+        #[serde(default)]
+        stamina: i64,
+    },
+    /// Allow a contract to update the given traits, replacing any traits it was granted before.
+    /// The staking contract can always update every trait.
+    GrantTraitUpdater {
+        address: String,
+        traits: Vec<TraitKind>,
+    },
+    /// Remove a contract from the trait updater registry
+    RevokeTraitUpdater {
+        address: String,
+    },
+    /// Let an address, e.g. a marketplace contract, receive transfers beyond the wallet limit.
+    /// The staking contract is always exempt.
+    AddWalletLimitExemption {
+        address: String,
+    },
+    /// Subject an address to the wallet limit again
+    RemoveWalletLimitExemption {
+        address: String,
+    },
+    /// Seed a new deployment with the tokens `ExportState` returns on the previous one, keeping
+    /// their ids, owners, traits and approvals. Only allowed until `SealImport` or the first mint,
+    /// and never on a deployment migrated with tokens already minted. Fails if the tokens or
+    /// `minted` would exceed `Config.token_supply`.
+    ImportTokens {
+        tokens: Vec<ExportedToken>,
+        /// The previous deployment's mint counter, so that new mints continue after it
+        #[serde(default)]
+        minted: Option<u64>,
+    },
+    /// Permanently disable `ImportTokens`
+    SealImport {},
+    /// Run the next batch of a migration step too large for one transaction. Minting, burning and
+    /// transfers are blocked until it's done. Owner only.
+    ResumeMigration {
+        limit: Option<u32>,
+    },
+    /// Halt or resume minting, cw721 transfers and trait updates
+    UpdatePauseFlags {
+        flags: PauseFlags,
+    },
+    /// Replace the collection-wide metadata settings
+    UpdateMetadataConfig {
+        metadata: MetadataConfig,
+    },
+    /// Destroy a token. Staked tokens must be withdrawn first.
+    Burn {
+        token_id: TokenId,
+    },
     /// BELOW ARE COPIED FROM CW721-BASE
     TransferNft {
         recipient: String,
-        token_id: String,
+        token_id: TokenId,
     },
     SendNft {
         contract: String,
-        token_id: String,
+        token_id: TokenId,
         msg: Binary,
     },
     Approve {
         spender: String,
-        token_id: String,
+        token_id: TokenId,
         expires: Option<Expiration>,
     },
     Revoke {
         spender: String,
-        token_id: String,
+        token_id: TokenId,
     },
     ApproveAll {
         operator: String,
@@ -193,15 +670,38 @@ pub enum ExecuteMsg {
     },
 }
 
-impl From<ExecuteMsg> for CW721ExecuteMsg<InternExtension> {
-    fn from(msg: ExecuteMsg) -> CW721ExecuteMsg<InternExtension> {
-        match msg {
+/// Returned when converting a message that has no cw721-base counterpart
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsupportedMessage {
+    /// Debug representation of the rejected message
+    pub msg: String,
+}
+
+impl fmt::Display for UnsupportedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot convert {} to a cw721 message", self.msg)
+    }
+}
+
+impl std::error::Error for UnsupportedMessage {}
+
+impl From<UnsupportedMessage> for StdError {
+    fn from(err: UnsupportedMessage) -> Self {
+        StdError::generic_err(err.to_string())
+    }
+}
+
+impl TryFrom<ExecuteMsg> for CW721ExecuteMsg<InternExtension> {
+    type Error = UnsupportedMessage;
+
+    fn try_from(msg: ExecuteMsg) -> Result<Self, Self::Error> {
+        Ok(match msg {
             ExecuteMsg::TransferNft {
                 recipient,
                 token_id,
             } => CW721ExecuteMsg::TransferNft {
                 recipient,
-                token_id,
+                token_id: token_id.to_string(),
             },
             ExecuteMsg::SendNft {
                 contract,
@@ -209,7 +709,7 @@ impl From<ExecuteMsg> for CW721ExecuteMsg<InternExtension> {
                 msg,
             } => CW721ExecuteMsg::SendNft {
                 contract,
-                token_id,
+                token_id: token_id.to_string(),
                 msg,
             },
             ExecuteMsg::Approve {
@@ -218,18 +718,23 @@ impl From<ExecuteMsg> for CW721ExecuteMsg<InternExtension> {
                 expires,
             } => CW721ExecuteMsg::Approve {
                 spender,
-                token_id,
+                token_id: token_id.to_string(),
                 expires,
             },
-            ExecuteMsg::Revoke { spender, token_id } => {
-                CW721ExecuteMsg::Revoke { spender, token_id }
-            }
+            ExecuteMsg::Revoke { spender, token_id } => CW721ExecuteMsg::Revoke {
+                spender,
+                token_id: token_id.to_string(),
+            },
             ExecuteMsg::ApproveAll { operator, expires } => {
                 CW721ExecuteMsg::ApproveAll { operator, expires }
             }
             ExecuteMsg::RevokeAll { operator } => CW721ExecuteMsg::RevokeAll { operator },
-            _ => panic!("cannot covert {:?} to CW721ExecuteMsg", msg),
-        }
+            _ => {
+                return Err(UnsupportedMessage {
+                    msg: format!("{:?}", msg),
+                })
+            }
+        })
     }
 }
 
@@ -240,33 +745,122 @@ pub enum QueryMsg {
     /// Returns the current contract config
     /// Return type: Config
     Config {},
+    /// Returns the collection-wide metadata settings
+    /// Return type: MetadataConfig
+    MetadataConfig {},
+    /// Returns which operations are halted
+    /// Return type: PauseFlags
+    PauseStatus {},
+    /// Returns the contract owner and any pending ownership proposal
+    /// Return type: OwnerResponse
+    Owner {},
     /// Returns all tokens owned by the given address, [] if unset.
-    /// Return type: InternTokensResponse.
+    /// Return type: InternTokensResponse, or InternTokensStakingResponse with `include_staking`.
     InternTokens {
         owner: String,
-        start_after: Option<String>,
+        start_after: Option<TokenId>,
+        start_before: Option<TokenId>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
+        /// Also look up each token in the staking contract
+        #[serde(default)]
+        include_staking: bool,
     },
     /// Lists all token_ids controlled by the contract.
     /// Return type: InternTokensResponse.
     AllInternTokens {
-        start_after: Option<String>,
+        start_after: Option<TokenId>,
+        start_before: Option<TokenId>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     },
     /// Returns metadata about one particular token, based on *ERC721 Metadata JSON Schema*
-    /// but directly from the contract: InternTokenInfo.
+    /// but directly from the contract: InternNftInfoResponse, or InternTokenStakingInfo with
+    /// `include_staking`.
     InternNftInfo {
-        token_id: String,
+        token_id: TokenId,
+        /// Also look up the token in the staking contract
+        #[serde(default)]
+        include_staking: bool,
     },
     /// Returns the number of tokens owned by the given address
     /// Return type: NumTokensResponse
     NumTokensForOwner {
         owner: String,
     },
+    /// Returns the current sale phase and how many tokens can still be minted, overall and
+    /// by the given address.
+    /// Return type: MintStatusResponse
+    MintStatus {
+        address: Option<String>,
+    },
+    /// Rolls sample starting traits from the given ranges, or the configured ones if unset.
+    /// Return type: StartingTraitsPreviewResponse
+    StartingTraitsPreview {
+        ranges: Option<TraitRanges>,
+        samples: Option<u32>,
+    },
+    /// Lists tokens matching all of the given filters, ordered by token id. Without `owner` but
+    /// with experience bounds, they are ordered by experience and then token id instead. At most
+    /// 300 tokens are read per call, so a page can hold fewer than `limit` tokens, or none, and
+    /// still come with a `next_cursor`: the last token read, to pass as `start_after`.
+    /// Return type: InternTokensResponse
+    SearchTokens {
+        owner: Option<String>,
+        min_experience: Option<u64>,
+        max_experience: Option<u64>,
+        min_gold: Option<u64>,
+        min_stamina: Option<u64>,
+        start_after: Option<TokenId>,
+        limit: Option<u32>,
+    },
+    /// Lists tokens from the most experienced down, ties ordered by token id. `start_after` is
+    /// the last token of the previous page.
+    /// Return type: InternTokensResponse
+    TopByExperience {
+        start_after: Option<TokenId>,
+        limit: Option<u32>,
+    },
+    /// Lists tokens from the richest down, paginated like `TopByExperience`
+    /// Return type: InternTokensResponse
+    TopByGold {
+        start_after: Option<TokenId>,
+        limit: Option<u32>,
+    },
+    /// Lists the contracts allowed to update traits besides the staking contract
+    /// Return type: TraitUpdatersResponse
+    TraitUpdaters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lists the addresses exempt from the wallet limit besides the staking contract
+    /// Return type: WalletLimitExemptionsResponse
+    WalletLimitExemptions {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Pages through every token with its id, for `ImportTokens` on a new deployment.
+    /// Tokens are ordered like `AllInternTokens`.
+    /// Return type: ExportStateResponse
+    ExportState {
+        start_after: Option<TokenId>,
+        limit: Option<u32>,
+    },
 
     // BELOW ARE COPIED FROM CW721-BASE
     OwnerOf {
-        token_id: String,
+        token_id: TokenId,
+        include_expired: Option<bool>,
+    },
+    /// Return type: Cw721ApprovalResponse. The owner is always approved, without expiry.
+    Approval {
+        token_id: TokenId,
+        spender: String,
+        include_expired: Option<bool>,
+    },
+    /// Return type: Cw721ApprovalsResponse
+    Approvals {
+        token_id: TokenId,
         include_expired: Option<bool>,
     },
     ApprovedForAll {
@@ -277,45 +871,62 @@ pub enum QueryMsg {
     },
     NumTokens {},
     ContractInfo {},
+    /// Returns the contract owner, who is the only one able to batch mint.
+    /// Return type: MinterResponse
+    Minter {},
     NftInfo {
-        token_id: String,
+        token_id: TokenId,
     },
     AllNftInfo {
-        token_id: String,
+        token_id: TokenId,
         include_expired: Option<bool>,
     },
+    /// Return type: Cw721TokensResponse
     Tokens {
         owner: String,
-        start_after: Option<String>,
+        start_after: Option<TokenId>,
+        start_before: Option<TokenId>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     },
+    /// Return type: Cw721TokensResponse
     AllTokens {
-        start_after: Option<String>,
+        start_after: Option<TokenId>,
+        start_before: Option<TokenId>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     },
 }
 
-impl From<QueryMsg> for CW721QueryMsg {
-    fn from(msg: QueryMsg) -> CW721QueryMsg {
-        match msg {
+impl TryFrom<QueryMsg> for CW721QueryMsg {
+    type Error = UnsupportedMessage;
+
+    fn try_from(msg: QueryMsg) -> Result<Self, Self::Error> {
+        Ok(match msg {
             QueryMsg::InternTokens {
                 owner,
                 start_after,
                 limit,
+                ..
             } => CW721QueryMsg::Tokens {
                 owner,
-                start_after,
+                start_after: start_after.map(|id| id.to_string()),
                 limit,
             },
-            QueryMsg::AllInternTokens { start_after, limit } => {
-                CW721QueryMsg::AllTokens { start_after, limit }
-            }
-            QueryMsg::InternNftInfo { token_id } => CW721QueryMsg::NftInfo { token_id },
+            QueryMsg::AllInternTokens {
+                start_after, limit, ..
+            } => CW721QueryMsg::AllTokens {
+                start_after: start_after.map(|id| id.to_string()),
+                limit,
+            },
+            QueryMsg::InternNftInfo { token_id, .. } => CW721QueryMsg::NftInfo {
+                token_id: token_id.to_string(),
+            },
             QueryMsg::OwnerOf {
                 token_id,
                 include_expired,
             } => CW721QueryMsg::OwnerOf {
-                token_id,
+                token_id: token_id.to_string(),
                 include_expired,
             },
             QueryMsg::ApprovedForAll {
@@ -331,44 +942,327 @@ impl From<QueryMsg> for CW721QueryMsg {
             },
             QueryMsg::NumTokens {} => CW721QueryMsg::NumTokens {},
             QueryMsg::ContractInfo {} => CW721QueryMsg::ContractInfo {},
-            QueryMsg::NftInfo { token_id } => CW721QueryMsg::NftInfo { token_id },
+            QueryMsg::Minter {} => CW721QueryMsg::Minter {},
+            QueryMsg::NftInfo { token_id } => CW721QueryMsg::NftInfo {
+                token_id: token_id.to_string(),
+            },
             QueryMsg::AllNftInfo {
                 token_id,
                 include_expired,
             } => CW721QueryMsg::AllNftInfo {
-                token_id,
+                token_id: token_id.to_string(),
                 include_expired,
             },
             QueryMsg::Tokens {
                 owner,
                 start_after,
                 limit,
+                ..
             } => CW721QueryMsg::Tokens {
                 owner,
-                start_after,
+                start_after: start_after.map(|id| id.to_string()),
                 limit,
             },
-            QueryMsg::AllTokens { start_after, limit } => {
-                CW721QueryMsg::AllTokens { start_after, limit }
+            QueryMsg::AllTokens {
+                start_after, limit, ..
+            } => CW721QueryMsg::AllTokens {
+                start_after: start_after.map(|id| id.to_string()),
+                limit,
+            },
+            _ => {
+                return Err(UnsupportedMessage {
+                    msg: format!("{:?}", msg),
+                })
             }
-            _ => panic!("cannot covert {:?} to CW721QueryMsg", msg),
+        })
+    }
+}
+
+/// Direction of the token lists, which are sorted by token number. Whatever the direction,
+/// `start_after` and `start_before` exclude their token and everything beyond it. To fetch the
+/// next page, pass the response's `next_cursor` as `start_after` when ascending, or as
+/// `start_before` when descending.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderBy {
+    Ascending,
+    Descending,
+}
+
+impl From<OrderBy> for Order {
+    fn from(order: OrderBy) -> Self {
+        match order {
+            OrderBy::Ascending => Order::Ascending,
+            OrderBy::Descending => Order::Descending,
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct InternTokensResponse {
-    pub tokens: Vec<InternTokenInfo>,
+    pub tokens: Vec<InternNftInfoResponse>,
+    /// The last token of this page if more tokens follow, see `OrderBy`
+    pub next_cursor: Option<TokenId>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InternTokenStakingInfo {
+    pub token: InternNftInfoResponse,
+    /// The token's record in the configured staking contract, None if it was never staked
+    pub staking: Option<StakingInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InternTokensStakingResponse {
+    pub tokens: Vec<InternTokenStakingInfo>,
+    pub next_cursor: Option<TokenId>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct OwnershipProposal {
+    pub owner: Addr,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct OwnerResponse {
+    pub owner: String,
+    pub proposal: Option<OwnershipProposal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintStatusResponse {
+    pub phase: MintPhaseKind,
+    /// Position of the active phase in `Config.mint_phases`, if any
+    pub phase_index: Option<u32>,
+    /// When the active phase ends, if any
+    pub phase_end: Option<Timestamp>,
+    /// The current mint price, unset while minting is closed
+    pub price: Option<Coin>,
+    pub minted: u64,
+    /// Tokens left before `Config.token_supply` is reached
+    pub remaining_supply: u64,
+    /// Tokens left before the active phase's supply cap is reached
+    pub phase_remaining_supply: Option<u64>,
+    /// Tokens the queried address can still mint right now
+    pub wallet_remaining: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct StartingTraitsPreviewResponse {
+    pub ranges: TraitRanges,
+    pub samples: Vec<InternExtension>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TraitUpdater {
+    pub address: String,
+    pub traits: Vec<TraitKind>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TraitUpdatersResponse {
+    pub updaters: Vec<TraitUpdater>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct WalletLimitExemptionsResponse {
+    pub addresses: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ExportedToken {
+    pub token_id: TokenId,
+    pub token: InternTokenInfo,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ExportStateResponse {
+    /// The number of tokens ever minted, including burned ones
+    pub minted: u64,
+    /// Whether `ImportTokens` is disabled
+    pub import_sealed: bool,
+    pub tokens: Vec<ExportedToken>,
+    /// The last token of this page if more tokens follow
+    pub next_cursor: Option<TokenId>,
+}
+
+/// Upgrades the contract state from the stored cw2 version. The settings below didn't exist in
+/// older versions; when left out, the migration keeps the current value or its default.
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
 #[serde(rename_all = "snake_case")]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Replaces `Config.starting_traits`
+    #[serde(default)]
+    pub starting_traits: Option<TraitRanges>,
+    /// Replaces `Config.burn_hook`
+    #[serde(default)]
+    pub burn_hook: Option<String>,
+    /// Replaces the metadata settings
+    #[serde(default)]
+    pub metadata: Option<MetadataConfig>,
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn trait_range_pick() {
+        let range = TraitRange { min: 10, max: 12 };
+        assert_eq!(range.pick(0), 10);
+        assert_eq!(range.pick(2), 12);
+        assert_eq!(range.pick(3), 10);
+        assert_eq!(TraitRange::fixed(5).pick(u64::MAX), 5);
+        assert_eq!(
+            TraitRange {
+                min: 0,
+                max: u64::MAX
+            }
+            .pick(42),
+            42
+        );
+    }
+
+    #[test]
+    fn unsupported_cw721_conversions() {
+        let err = CW721ExecuteMsg::<InternExtension>::try_from(ExecuteMsg::Burn {
+            token_id: TokenId::new(1),
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot convert Burn { token_id: TokenId(1) } to a cw721 message"
+        );
+        assert!(
+            CW721ExecuteMsg::<InternExtension>::try_from(ExecuteMsg::TransferNft {
+                recipient: "recipient".to_string(),
+                token_id: TokenId::new(1),
+            })
+            .is_ok()
+        );
+
+        let err = CW721QueryMsg::try_from(QueryMsg::Config {}).unwrap_err();
+        assert_eq!(
+            StdError::from(err),
+            StdError::generic_err("cannot convert Config to a cw721 message")
+        );
+        assert_eq!(
+            CW721QueryMsg::try_from(QueryMsg::NumTokens {}).unwrap(),
+            CW721QueryMsg::NumTokens {}
+        );
+    }
+
+    #[test]
+    fn intern_level() {
+        let level = |experience| {
+            InternExtension {
+                experience,
+                gold: 0,
+                stamina: 0,
+            }
+            .level()
+        };
+        assert_eq!(level(0), 1);
+        assert_eq!(level(99), 1);
+        assert_eq!(level(100), 2);
+        assert_eq!(level(399), 2);
+        assert_eq!(level(400), 3);
+        assert_eq!(level(10_000), 11);
+        assert_eq!(level(u64::MAX), 429_496_730);
+    }
+
+    #[test]
+    fn trait_filter_matches() {
+        let traits = InternExtension {
+            experience: 100,
+            gold: 50,
+            stamina: 10,
+        };
+        assert!(TraitFilter::default().matches(&traits));
+        assert!(TraitFilter {
+            min_experience: Some(100),
+            max_experience: Some(100),
+            min_gold: Some(50),
+            min_stamina: Some(10),
+        }
+        .matches(&traits));
+        assert!(!TraitFilter {
+            min_experience: Some(101),
+            ..TraitFilter::default()
+        }
+        .matches(&traits));
+        assert!(!TraitFilter {
+            max_experience: Some(99),
+            ..TraitFilter::default()
+        }
+        .matches(&traits));
+        assert!(!TraitFilter {
+            min_gold: Some(51),
+            ..TraitFilter::default()
+        }
+        .matches(&traits));
+        assert!(!TraitFilter {
+            min_stamina: Some(11),
+            ..TraitFilter::default()
+        }
+        .matches(&traits));
+    }
+
+    #[test]
+    fn metadata_config_templates() {
+        let metadata = MetadataConfig {
+            base_uri: Some("ipfs://cid/{id}.json".to_string()),
+            external_url: Some("https://interns.example/{id}".to_string()),
+            background_color: None,
+        };
+        assert_eq!(
+            metadata.token_uri("7"),
+            Some("ipfs://cid/7.json".to_string())
+        );
+        assert_eq!(
+            metadata.external_url("7"),
+            Some("https://interns.example/7".to_string())
+        );
+
+        // without a placeholder the id is appended to the base uri
+        let metadata = MetadataConfig {
+            base_uri: Some("ipfs://cid/".to_string()),
+            ..MetadataConfig::default()
+        };
+        assert_eq!(metadata.token_uri("7"), Some("ipfs://cid/7".to_string()));
+        assert_eq!(metadata.external_url("7"), None);
+
+        for color in ["1b1f3b", "FFFFFF"].iter() {
+            let metadata = MetadataConfig {
+                background_color: Some(color.to_string()),
+                ..MetadataConfig::default()
+            };
+            assert!(metadata.validate().is_ok());
+        }
+        for color in ["#1b1f3", "1b1f3", "1b1f3g", ""].iter() {
+            let metadata = MetadataConfig {
+                background_color: Some(color.to_string()),
+                ..MetadataConfig::default()
+            };
+            assert!(metadata.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn trait_ranges_validate() {
+        assert!(TraitRanges::default().validate().is_ok());
+
+        let ranges = TraitRanges {
+            gold: TraitRange { min: 2, max: 1 },
+            ..TraitRanges::default()
+        };
+        assert_eq!(
+            ranges.validate().unwrap_err(),
+            StdError::generic_err("invalid gold range: min is greater than max")
+        );
+    }
+
     #[test]
     fn intern_token_info_as_cw721_nft_info() {
         let info = InternTokenInfo {
@@ -382,7 +1276,8 @@ mod tests {
                 gold: 100,
                 stamina: 100,
             },
-        };
+        }
+        .into_response("1", &MetadataConfig::default());
 
         assert_eq!(
             info.as_cw721_nft_info(),
@@ -391,7 +1286,7 @@ mod tests {
                 extension: Cw721Metadata {
                     name: Some("intern #1".to_string()),
                     description: Some("testing description".to_string()),
-                    image: None,
+                    image: Some(info.image_data()),
                     attributes: Some(vec![
                         Cw721Trait {
                             display_type: None,
@@ -409,7 +1304,7 @@ mod tests {
                             value: "100".to_string(),
                         },
                     ]),
-                    image_data: None,
+                    image_data: Some(info.image_data()),
                     animation_url: None,
                     youtube_url: None,
                     external_url: None,
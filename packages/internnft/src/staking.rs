@@ -3,6 +3,8 @@ use cw721::Cw721ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::token_id::TokenId;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
@@ -13,13 +15,18 @@ pub struct Config {
     pub exp_constant: u64,
 }
 
+/// Namespace of the staking contract's `StakingInfo` map, keyed by numeric token id
+pub const STAKING_INFO_NAMESPACE: &str = "stakers";
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct StakingInfo {
     pub staked: bool,
     pub last_action_block_time: u64,
+    /// Stamina as of `last_action_block_time`. `GetStakingInfo` returns it brought up to the
+    /// current block.
     pub current_stamina: u64,
-    pub token_id: String,
+    pub token_id: TokenId,
     pub owner: Addr,
     pub staking_type: String,
 }
@@ -48,7 +55,20 @@ pub enum ExecuteMsg {
         exp_constant: u64,
     },
     /// Allows the calling user to withdraw the specified nft if they own it.
-    WithdrawNft { token_id: String },
+    WithdrawNft { token_id: TokenId },
+    /// Run the next batch of a migration step too large for one transaction. Staking and
+    /// withdrawing are blocked until it's done. Owner only.
+    ResumeMigration { limit: Option<u32> },
+}
+
+/// Upgrades the contract state from the stored cw2 version, optionally retuning the rewards
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {
+    #[serde(default)]
+    pub stamina_constant: Option<u64>,
+    #[serde(default)]
+    pub exp_constant: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -61,7 +81,10 @@ pub enum Cw721HookMsg {
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     GetConfig {},
-    GetStakingInfo { token_id: String },
+    /// Return type: Option<StakingInfo>, null for tokens that were never staked
+    GetStakingInfo {
+        token_id: TokenId,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
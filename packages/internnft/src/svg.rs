@@ -0,0 +1,107 @@
+use crate::nft::InternExtension;
+
+const WIDTH: u32 = 300;
+const HEIGHT: u32 = 400;
+const FONT: &str = "monospace";
+
+/// Renders the stat card shown as an intern's image. The output only depends on the
+/// arguments, so every query for the same token state returns the same image.
+pub fn render_intern_card(name: &str, traits: &InternExtension) -> String {
+    let stats = [
+        ("Experience", traits.experience),
+        ("Gold", traits.gold),
+        ("Stamina", traits.stamina),
+    ];
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = WIDTH,
+        h = HEIGHT,
+    );
+    svg.push_str(&format!(
+        r##"<rect width="{}" height="{}" rx="16" fill="#1b1f3b"/>"##,
+        WIDTH, HEIGHT
+    ));
+    svg.push_str(&format!(
+        r##"<text x="{}" y="64" font-family="{}" font-size="24" fill="#ffffff" text-anchor="middle">{}</text>"##,
+        WIDTH / 2,
+        FONT,
+        escape_xml(name)
+    ));
+    svg.push_str(&format!(
+        r##"<text x="{}" y="108" font-family="{}" font-size="18" fill="#f5c542" text-anchor="middle">Level {}</text>"##,
+        WIDTH / 2,
+        FONT,
+        traits.level()
+    ));
+    for (i, (label, value)) in stats.iter().enumerate() {
+        let y = 180 + 60 * i as u32;
+        svg.push_str(&format!(
+            r##"<text x="32" y="{}" font-family="{}" font-size="16" fill="#a0a8d0">{}</text>"##,
+            y, FONT, label
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{}" y="{}" font-family="{}" font-size="16" fill="#ffffff" text-anchor="end">{}</text>"##,
+            WIDTH - 32,
+            y,
+            FONT,
+            value
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Wraps an SVG document in a base64 data URI, which wallets can display without fetching
+/// anything
+pub fn svg_data_uri(svg: &str) -> String {
+    format!("data:image/svg+xml;base64,{}", base64::encode(svg))
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_card() {
+        let traits = InternExtension {
+            experience: 400,
+            gold: 25,
+            stamina: 80,
+        };
+        let svg = render_intern_card("intern #1", &traits);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(">intern #1</text>"));
+        assert!(svg.contains(">Level 3</text>"));
+        assert!(svg.contains(">400</text>"));
+        assert!(svg.contains(">25</text>"));
+        assert!(svg.contains(">80</text>"));
+        assert_eq!(svg, render_intern_card("intern #1", &traits));
+
+        let svg = render_intern_card("<intern & co>", &traits);
+        assert!(svg.contains(">&lt;intern &amp; co&gt;</text>"));
+    }
+
+    #[test]
+    fn data_uri() {
+        let uri = svg_data_uri("<svg/>");
+        assert_eq!(uri, "data:image/svg+xml;base64,PHN2Zy8+");
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::str::FromStr;
+
+use cosmwasm_std::{StdError, StdResult};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// The numeric identifier of an intern, as used in every message and query.
+///
+/// Serializes as a decimal string, like the cw721 token ids, but only canonical non-negative
+/// integers deserialize: prefixed ids like "intern #1", signs and leading zeros are rejected.
+/// Storage keys add the collection's token prefix, see `full` and `from_full`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenId(u64);
+
+impl TokenId {
+    pub const fn new(id: u64) -> Self {
+        TokenId(id)
+    }
+
+    pub fn u64(&self) -> u64 {
+        self.0
+    }
+
+    /// The storage key and name of the token, e.g. "intern #1"
+    pub fn full(&self, prefix: &str) -> String {
+        format!("{}{}", prefix, self.0)
+    }
+
+    /// Parses a storage key back into its numeric id
+    pub fn from_full(prefix: &str, full_token_id: &str) -> StdResult<Self> {
+        match full_token_id.strip_prefix(prefix) {
+            Some(numeric) => numeric.parse(),
+            None => Err(StdError::generic_err(format!(
+                "expected full token identifier, like '{}123'",
+                prefix
+            ))),
+        }
+    }
+}
+
+impl From<u64> for TokenId {
+    fn from(id: u64) -> Self {
+        TokenId(id)
+    }
+}
+
+impl fmt::Display for TokenId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for TokenId {
+    type Err = StdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let canonical = !s.is_empty()
+            && s.bytes().all(|b| b.is_ascii_digit())
+            && (s == "0" || !s.starts_with('0'));
+        if !canonical {
+            return Err(StdError::generic_err("expected numeric token identifier"));
+        }
+        s.parse()
+            .map(TokenId)
+            .map_err(|_| StdError::generic_err("expected numeric token identifier"))
+    }
+}
+
+impl Serialize for TokenId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(TokenIdVisitor)
+    }
+}
+
+struct TokenIdVisitor;
+
+impl<'de> Visitor<'de> for TokenIdVisitor {
+    type Value = TokenId;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a numeric token identifier string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse()
+            .map_err(|err: StdError| E::custom(err.to_string()))
+    }
+}
+
+impl JsonSchema for TokenId {
+    fn schema_name() -> String {
+        "TokenId".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{from_slice, to_vec};
+
+    #[test]
+    fn parse() {
+        assert_eq!("0".parse::<TokenId>().unwrap(), TokenId::new(0));
+        assert_eq!("123".parse::<TokenId>().unwrap(), TokenId::new(123));
+        for malformed in [
+            "",
+            "intern #1",
+            "abc",
+            "-1",
+            "+1",
+            "01",
+            " 1",
+            "18446744073709551616",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                malformed.parse::<TokenId>().unwrap_err(),
+                StdError::generic_err("expected numeric token identifier"),
+                "{}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn full() {
+        let id = TokenId::new(7);
+        assert_eq!(id.full("intern #"), "intern #7");
+        assert_eq!(TokenId::from_full("intern #", "intern #7").unwrap(), id);
+        assert_eq!(
+            TokenId::from_full("intern #", "other #7").unwrap_err(),
+            StdError::generic_err("expected full token identifier, like 'intern #123'")
+        );
+        assert!(TokenId::from_full("intern #", "intern #abc").is_err());
+    }
+
+    #[test]
+    fn serde() {
+        let id = TokenId::new(42);
+        assert_eq!(to_vec(&id).unwrap(), br#""42""#.to_vec());
+        assert_eq!(from_slice::<TokenId>(br#""42""#).unwrap(), id);
+        assert!(from_slice::<TokenId>(br#""intern #42""#).is_err());
+        assert!(from_slice::<TokenId>(br#"42"#).is_err());
+    }
+}